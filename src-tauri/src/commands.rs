@@ -1,95 +1,651 @@
 use std::sync::Mutex;
 use tauri::State;
-use std::path::PathBuf;
-use crate::db::{self, Clip, Segment};
-use crate::file_manager::WorkingDirectory;
-use crate::ffmpeg::{self, VideoInfo};
+use std::path::{Path, PathBuf};
+use crate::db::{self, Clip, ClipStatus, Segment, StatusChange};
+use crate::file_manager::{FolderConfig, WorkingDirectory};
+use crate::folder_lock::{FolderLock, LockError, LockInfo};
+use crate::fingerprint::{self, DuplicateGroup};
+use crate::history::{self, Bounds, SegmentEdit};
+use crate::ingest;
+use crate::integrity::{self, IntegrityReport, RepairOptions, RepairReport};
+use crate::projects::{Project, ProjectRegistry};
+use crate::proxy::{self, ProxyQueue};
+use crate::watcher::InWatcher;
+use crate::segments::{self, OverlapPolicy};
+use crate::storage::{self, CleanupPlan};
+use crate::server::{MediaServerInfo, MediaServerState};
+use crate::ffmpeg::{self, FrameFormat, OutputContainer, VideoInfo};
+use crate::frames::FrameCache;
+use crate::detect::{self, HighlightOptions, MotionOptions, SuggestedSegment};
 use uuid::Uuid;
-use chrono::Utc;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
 pub struct AppState {
     pub db: Mutex<Option<rusqlite::Connection>>,
     pub work_dir: Mutex<Option<WorkingDirectory>>,
+    pub watcher: Mutex<Option<InWatcher>>,
+    /// Held while this instance may write to the folder; `None` when it was
+    /// opened read-only.
+    pub folder_lock: Mutex<Option<FolderLock>>,
 }
 
+/// Returned when a command would modify a folder opened read-only.
+const READ_ONLY_ERROR: &str = "This working folder is open read-only because another ClipTrim instance is using it";
+
+/// Returned when a clip already has an export queued or running.
+const ALREADY_EXPORTING_ERROR: &str = "This clip is already queued or being exported";
+
+/// Exports run one at a time; clips wait in `queued` for their turn.
+static EXPORT_SLOT: std::sync::LazyLock<tauri::async_runtime::Mutex<()>> = std::sync::LazyLock::new(Default::default);
+
+/// Moves a clip to `to` and records the change in one transaction.
+fn change_clip_status(conn: &rusqlite::Connection, clip_id: &str, to: ClipStatus) -> Result<ClipStatus, String> {
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let from = db::update_clip_status(&tx, clip_id, to).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(from)
+}
+
+/// Fails unless a working folder is open and this instance holds its lock.
+fn ensure_writable(state: &AppState) -> Result<(), String> {
+    if state.work_dir.lock().unwrap().is_none() {
+        return Err("Working folder not set".to_string());
+    }
+    if state.folder_lock.lock().unwrap().as_ref().is_none_or(FolderLock::is_lost) {
+        return Err(READ_ONLY_ERROR.to_string());
+    }
+    Ok(())
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FolderLockLostPayload {
+    host: Option<String>,
+    pid: Option<u32>,
+}
+
+/// Drops to read-only once another instance has taken the folder over: the
+/// lock is released, the IN watcher stopped and the UI told.
+fn on_folder_lock_lost(app_handle: tauri::AppHandle, holder: Option<LockInfo>) {
+    use tauri::{Emitter, Manager};
+    let state = app_handle.state::<AppState>();
+    let mut lock_guard = state.folder_lock.lock().unwrap();
+    // A folder opened since then has its own lock
+    if !lock_guard.as_ref().is_some_and(FolderLock::is_lost) {
+        return;
+    }
+    *lock_guard = None;
+    drop(lock_guard);
+    *state.watcher.lock().unwrap() = None;
+    let _ = app_handle.emit("folder-lock-lost", FolderLockLostPayload {
+        host: holder.as_ref().map(|info| info.host.clone()),
+        pid: holder.as_ref().map(|info| info.pid),
+    });
+}
+
+/// Why a working folder could not be opened. `Locked` lets the UI offer to
+/// open the folder read-only instead.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SelectFolderError {
+    Locked {
+        message: String,
+        host: String,
+        pid: u32,
+        acquired_at: i64,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl From<String> for SelectFolderError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<&str> for SelectFolderError {
+    fn from(message: &str) -> Self {
+        Self::Other { message: message.to_string() }
+    }
+}
+
+impl From<LockError> for SelectFolderError {
+    fn from(e: LockError) -> Self {
+        let message = e.to_string();
+        match e {
+            LockError::Held(info) => Self::Locked {
+                message,
+                host: info.host,
+                pid: info.pid,
+                acquired_at: info.acquired_at,
+            },
+            LockError::Io(_) => Self::Other { message },
+        }
+    }
+}
+
+/// Opens a working folder. Unless `read_only` is set, the folder is locked
+/// against other instances first; if another instance holds it, this fails
+/// with `SelectFolderError::Locked` and the caller may retry read-only.
 #[tauri::command]
 pub fn select_working_folder(
+    app_handle: tauri::AppHandle,
     path: String,
+    read_only: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let wd = WorkingDirectory::new(&PathBuf::from(&path)).map_err(|e| e.to_string())?;
+    projects: State<'_, ProjectRegistry>,
+) -> Result<(), SelectFolderError> {
+    let read_only = read_only.unwrap_or(false);
+    // A read-only instance must not create folders or probe files in a
+    // folder someone else holds, or on a share it cannot write to
+    let wd = if read_only {
+        WorkingDirectory::open_read_only(&PathBuf::from(&path))
+    } else {
+        WorkingDirectory::new(&PathBuf::from(&path))
+    }
+    .map_err(|e| e.to_string())?;
+
+    let (folder_lock, conn) = if read_only {
+        (None, db::open_read_only(&wd.db_path).map_err(|e| e.to_string())?)
+    } else {
+        let cliptrim_dir = wd.db_path.parent().ok_or("Invalid working folder")?;
+        let lost_handle = app_handle.clone();
+        let folder_lock = FolderLock::acquire(cliptrim_dir, move |holder| on_folder_lock_lost(lost_handle, holder))?;
+        // Init DB
+        (Some(folder_lock), db::init_db(&wd.db_path).map_err(|e| e.to_string())?)
+    };
+
+    // Stop watching the previous folder before switching; read-only
+    // instances leave ingesting to the lock holder.
+    *state.watcher.lock().unwrap() = None;
+    let watcher = if read_only {
+        None
+    } else {
+        InWatcher::start(app_handle, &wd)
+            .map_err(|e| eprintln!("[watcher] could not watch {:?}: {}", wd.in_dir, e))
+            .ok()
+    };
     
-    // Init DB
-    let conn = db::init_db(&wd.db_path).map_err(|e| e.to_string())?;
+    let status_counts = db::count_clips_by_status(&conn).unwrap_or_default();
+    if let Err(e) = projects.touch(&wd.root, status_counts) {
+        eprintln!("[projects] could not record {:?}: {}", wd.root, e);
+    }
     
     *state.db.lock().unwrap() = Some(conn);
     *state.work_dir.lock().unwrap() = Some(wd);
+    *state.watcher.lock().unwrap() = watcher;
+    *state.folder_lock.lock().unwrap() = folder_lock;
     
     Ok(())
 }
 
+/// Known working folders, pinned first, then most recently opened.
 #[tauri::command]
-pub fn scan_and_get_clips(state: State<'_, AppState>) -> Result<Vec<Clip>, String> {
-    let wd_guard = state.work_dir.lock().unwrap();
-    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
-    
+pub fn list_projects(state: State<'_, AppState>, projects: State<'_, ProjectRegistry>) -> Result<Vec<Project>, String> {
+    // Refresh the totals of the open folder so they are current
+    let root = state.work_dir.lock().unwrap().as_ref().map(|wd| wd.root.clone());
+    if let Some(root) = root {
+        let db_guard = state.db.lock().unwrap();
+        if let Some(conn) = db_guard.as_ref() {
+            let status_counts = db::count_clips_by_status(conn).map_err(|e| e.to_string())?;
+            projects.update_counts(&root, status_counts).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(projects.list())
+}
+
+#[tauri::command]
+pub fn add_project(path: String, projects: State<'_, ProjectRegistry>) -> Result<Project, String> {
+    let path = PathBuf::from(&path);
+    if !path.is_dir() {
+        return Err(format!("{} is not a folder", path.display()));
+    }
+    projects.add(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_project(path: String, projects: State<'_, ProjectRegistry>) -> Result<(), String> {
+    projects.remove(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_project_pinned(path: String, pinned: bool, projects: State<'_, ProjectRegistry>) -> Result<(), String> {
+    projects.set_pinned(&path, pinned).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn scan_and_get_clips(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+    proxies: State<'_, ProxyQueue>,
+) -> Result<Vec<Clip>, String> {
+    if ensure_writable(&state).is_err() {
+        return get_clips(state);
+    }
+
+    // Scan IN folder
+    let in_files = {
+        let wd_guard = state.work_dir.lock().unwrap();
+        let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+        wd.scan_in_folder().map_err(|e| e.to_string())?
+    };
+    ingest::ingest_files(&app_handle, in_files).await?;
+    // Backfill fingerprints for clips ingested by older versions
+    fingerprint::spawn_hash_pass(app_handle.clone());
+
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
-    
-    // Scan IN folder
-    let in_files = wd.scan_in_folder().map_err(|e| e.to_string())?;
-    
-    for file in in_files {
-        let name = file.file_name().unwrap().to_string_lossy().to_string();
-        // Check if exists
-        if let Ok(None) = db::get_clip_by_original_name(conn, &name) {
-            let clip = Clip {
-                id: Uuid::new_v4().to_string(),
-                original_name: name.clone(),
-                backup_path: file.to_string_lossy().to_string(), // Temporarily store IN path
-                status: "new".to_string(),
-                created_at: Utc::now().timestamp(),
-            };
-            db::insert_clip(conn, &clip).map_err(|e| e.to_string())?;
-        }
+
+    let clips = db::get_clips(conn).map_err(|e| e.to_string())?;
+    for clip in clips.iter().filter(|c| proxy::needs_check(c)) {
+        proxies.enqueue(&clip.id);
     }
-    
+
     // Return all clips
+    Ok(clips)
+}
+
+/// Returns the clips already in the DB without touching the filesystem.
+#[tauri::command]
+pub fn get_clips(state: State<'_, AppState>) -> Result<Vec<Clip>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
     db::get_clips(conn).map_err(|e| e.to_string())
 }
 
+/// Groups of clips with identical content, e.g. the same capture copied
+/// into IN twice.
 #[tauri::command]
-pub fn open_clip(clip_id: String, state: State<'_, AppState>) -> Result<Clip, String> {
-    let wd_guard = state.work_dir.lock().unwrap();
-    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
-    
+pub fn find_duplicate_clips(state: State<'_, AppState>) -> Result<Vec<DuplicateGroup>, String> {
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
-    
     let clips = db::get_clips(conn).map_err(|e| e.to_string())?;
-    let mut target_clip = clips.into_iter().find(|c| c.id == clip_id).ok_or("Clip not found")?;
+    Ok(fingerprint::duplicate_groups(clips))
+}
+
+/// Sets a clip's status by hand, e.g. to skip or reject it. Only `new`,
+/// `in_progress`, `skipped` and `rejected` may be chosen; transitions the
+/// state machine does not allow are refused.
+#[tauri::command]
+pub fn set_clip_status(clip_id: String, status: ClipStatus, state: State<'_, AppState>) -> Result<Clip, String> {
+    ensure_writable(&state)?;
+    if !status.is_user_settable() {
+        return Err(format!("A clip cannot be marked {} by hand; exports set that status", status));
+    }
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    change_clip_status(conn, &clip_id, status)?;
+    db::get_clip_by_id(conn, &clip_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Clip not found".to_string())
+}
+
+#[tauri::command]
+pub fn get_clip_status_history(clip_id: String, state: State<'_, AppState>) -> Result<Vec<StatusChange>, String> {
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    db::get_status_history(conn, &clip_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_folder_config(state: State<'_, AppState>) -> Result<FolderConfig, String> {
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+    Ok(wd.config.clone())
+}
+
+/// Saves new folder settings. IN and BACKUP can only be moved while the
+/// folder has no clips, since every clip stores paths inside them.
+#[tauri::command]
+pub fn update_folder_config(
+    app_handle: tauri::AppHandle,
+    config: FolderConfig,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    ensure_writable(&state)?;
+
+    let mut wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_mut().ok_or("Working folder not set")?;
+    if wd.relocates_clips(&config) {
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        if db::count_clips(conn).map_err(|e| e.to_string())? > 0 {
+            return Err("IN and BACKUP cannot be moved once the folder has clips".to_string());
+        }
+    }
+    wd.save_config(config).map_err(|e| e.to_string())?;
+
+    // IN or the accepted extensions may have changed
+    let mut watcher_guard = state.watcher.lock().unwrap();
+    *watcher_guard = None;
+    *watcher_guard = InWatcher::start(app_handle, wd)
+        .map_err(|e| eprintln!("[watcher] could not watch {:?}: {}", wd.in_dir, e))
+        .ok();
+    Ok(())
+}
+
+#[derive(Clone, serde::Serialize)]
+struct BackupProgressPayload {
+    clip_id: String,
+    percent: u8,
+}
+
+#[tauri::command]
+pub async fn open_clip(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    state: State<'_, AppState>,
+    proxies: State<'_, ProxyQueue>,
+) -> Result<Clip, String> {
+    use tauri::Emitter;
+
+    let (wd, mut target_clip) = {
+        let wd_guard = state.work_dir.lock().unwrap();
+        let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+
+        let clips = db::get_clips(conn).map_err(|e| e.to_string())?;
+        let target_clip = clips.into_iter().find(|c| c.id == clip_id).ok_or("Clip not found")?;
+        (wd.clone(), target_clip)
+    };
     
-    if target_clip.status == "new" {
-        // Move to BACKUP
-        let path = PathBuf::from(&target_clip.backup_path);
-        if path.exists() {
-            let new_path = wd.move_to_backup(&path).map_err(|e| e.to_string())?;
-            
-            // Update DB
-            target_clip.backup_path = new_path.to_string_lossy().to_string();
-            target_clip.status = "in_progress".to_string();
-            
-            conn.execute(
-                "UPDATE clips SET backup_path = ?1, status = ?2 WHERE id = ?3",
-                rusqlite::params![target_clip.backup_path, target_clip.status, target_clip.id],
-            ).map_err(|e| e.to_string())?;
+    // Read-only instances view clips where they are
+    if ensure_writable(&state).is_err() {
+        return Ok(target_clip);
+    }
+
+    let path = PathBuf::from(&target_clip.backup_path);
+    let previous_status = target_clip.status;
+    // Skipped or rejected clips may never have been opened either
+    let unopened = matches!(previous_status, ClipStatus::New | ClipStatus::Skipped | ClipStatus::Rejected)
+        && !wd.is_in_backup(&path);
+    if unopened && path.exists() {
+        // Claim the clip first so the IN watcher does not drop it when the
+        // original disappears mid-move.
+        {
+            let db_guard = state.db.lock().unwrap();
+            let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+            change_clip_status(conn, &target_clip.id, ClipStatus::InProgress)?;
+        }
+        target_clip.status = ClipStatus::InProgress;
+
+        // Secure the original in BACKUP; cross-device copies can take a while.
+        let progress_handle = app_handle.clone();
+        let progress_clip_id = target_clip.id.clone();
+        let backup = tauri::async_runtime::spawn_blocking(move || {
+            let mut last_percent = None;
+            wd.backup_original(&path, |copied, total| {
+                let percent = (copied * 100).checked_div(total).unwrap_or(100) as u8;
+                if last_percent != Some(percent) {
+                    last_percent = Some(percent);
+                    let _ = progress_handle.emit("backup-progress", BackupProgressPayload {
+                        clip_id: progress_clip_id.clone(),
+                        percent,
+                    });
+                }
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r.map_err(|e| e.to_string()));
+
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        match backup {
+            Ok(new_path) => {
+                // Update DB
+                target_clip.backup_path = new_path.to_string_lossy().to_string();
+                conn.execute(
+                    "UPDATE clips SET backup_path = ?1 WHERE id = ?2",
+                    rusqlite::params![target_clip.backup_path, target_clip.id],
+                ).map_err(|e| e.to_string())?;
+            }
+            Err(e) => {
+                let _ = change_clip_status(conn, &target_clip.id, previous_status);
+                return Err(format!("Failed to back up original: {}", e));
+            }
+        }
+    } else if matches!(previous_status, ClipStatus::Skipped | ClipStatus::Rejected) {
+        // Reopening a set-aside clip picks the work back up
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        change_clip_status(conn, &target_clip.id, ClipStatus::InProgress)?;
+        target_clip.status = ClipStatus::InProgress;
+    }
+
+    // Segment validation needs the length of clips ingested before it was recorded
+    if target_clip.duration_ms.is_none() {
+        match ffmpeg::probe_media(&app_handle, Path::new(&target_clip.backup_path)).await {
+            Ok(probe) => {
+                if let Some(duration_ms) = probe.duration_ms() {
+                    target_clip.duration_ms = Some(duration_ms);
+                    target_clip.fps = probe.video.map(|v| v.fps);
+                    let db_guard = state.db.lock().unwrap();
+                    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+                    db::update_clip_timing(conn, &target_clip.id, duration_ms, target_clip.fps)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            Err(e) => eprintln!("[open] could not probe {}: {}", target_clip.backup_path, e),
         }
     }
+
+    if proxy::needs_work_on_open(&target_clip) {
+        proxies.enqueue(&target_clip.id);
+    }
     
     Ok(target_clip)
 }
 
+/// Undoes `open_clip`: moves the original from BACKUP back into IN and
+/// resets the clip to `new`. Segments are dropped unless `keep_segments`.
+#[tauri::command]
+pub async fn restore_clip(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    keep_segments: bool,
+    state: State<'_, AppState>,
+) -> Result<Clip, String> {
+    use tauri::Emitter;
+
+    ensure_writable(&state)?;
+
+    let (wd, clip) = {
+        let wd_guard = state.work_dir.lock().unwrap();
+        let wd = wd_guard.as_ref().ok_or("Working folder not set")?.clone();
+
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        let clip = db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+        (wd, clip)
+    };
+    if clip.status == ClipStatus::New {
+        return Err("Clip has not been opened, nothing to restore".to_string());
+    }
+
+    let source = PathBuf::from(&clip.backup_path);
+    if !source.exists() {
+        return Err(format!("Original not found at {}", clip.backup_path));
+    }
+    // Referenced originals never left IN.
+    let restored = if wd.is_in_backup(&source) {
+        let progress_handle = app_handle.clone();
+        let progress_clip_id = clip.id.clone();
+        let relative_path = clip.relative_path.clone();
+        let fingerprint = clip.fingerprint.clone();
+        let content_hash = clip.content_hash.clone();
+        let restore_wd = wd.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut last_percent = None;
+            restore_wd.restore_to_in(
+                &source,
+                &relative_path,
+                fingerprint.as_deref(),
+                content_hash.as_deref(),
+                |copied, total| {
+                    let percent = (copied * 100).checked_div(total).unwrap_or(100) as u8;
+                    if last_percent != Some(percent) {
+                        last_percent = Some(percent);
+                        let _ = progress_handle.emit("restore-progress", BackupProgressPayload {
+                            clip_id: progress_clip_id.clone(),
+                            percent,
+                        });
+                    }
+                },
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to restore original: {}", e))?
+    } else {
+        source
+    };
+
+    let original_name = restored.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let relative_path = wd.relative_in_path(&restored).unwrap_or(clip.relative_path);
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    db::reset_clip_to_new(&tx, &clip_id, &original_name, &relative_path, &restored.to_string_lossy())
+        .map_err(|e| e.to_string())?;
+    if !keep_segments {
+        db::delete_segments_for_clip(&tx, &clip_id).map_err(|e| e.to_string())?;
+        db::delete_segment_operations(&tx, &clip_id).map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or_else(|| "Clip not found".to_string())
+}
+
+/// What `remove_clip` deletes (or, with `dry_run`, would delete).
+#[derive(Debug, serde::Serialize)]
+pub struct RemovalSummary {
+    pub clip_id: String,
+    pub original_name: String,
+    pub segment_count: usize,
+    /// Files deleted from disk, or that would be.
+    pub files: Vec<String>,
+    /// Files that could not be deleted.
+    pub failed: Vec<String>,
+}
+
+/// Forgets a clip: deletes its DB rows, segments included, and its cached
+/// proxy/HLS data. With `delete_backup` its original in BACKUP is deleted too,
+/// with `delete_outputs` every file it exported to OUT. Originals still in IN
+/// are never deleted and will be picked up again by the next scan.
+///
+/// Call with `dry_run` first to show the user what will be removed.
+#[tauri::command]
+pub fn remove_clip(
+    clip_id: String,
+    delete_backup: bool,
+    delete_outputs: bool,
+    dry_run: bool,
+    state: State<'_, AppState>,
+) -> Result<RemovalSummary, String> {
+    if !dry_run {
+        ensure_writable(&state)?;
+    }
+
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+
+    let clip = db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+    let segment_count = db::get_segments(conn, &clip_id).map_err(|e| e.to_string())?.len();
+
+    let mut files = Vec::new();
+    let backup = PathBuf::from(&clip.backup_path);
+    if delete_backup && wd.is_in_backup(&backup) && backup.exists() {
+        files.push(clip.backup_path.clone());
+    }
+    if delete_outputs {
+        let outputs = db::get_export_paths(conn, &clip_id).map_err(|e| e.to_string())?;
+        files.extend(outputs.into_iter().filter(|p| PathBuf::from(p).exists()));
+    }
+
+    let mut summary = RemovalSummary {
+        clip_id: clip.id.clone(),
+        original_name: clip.original_name.clone(),
+        segment_count,
+        files,
+        failed: Vec::new(),
+    };
+    if dry_run {
+        return Ok(summary);
+    }
+
+    db::delete_clip(conn, &clip_id).map_err(|e| e.to_string())?;
+
+    // Derived data is always dropped
+    if let Some(proxy_path) = &clip.proxy_path {
+        let _ = std::fs::remove_file(proxy_path);
+    }
+    let _ = std::fs::remove_dir_all(wd.hls_dir.join(&clip.id));
+
+    let (deleted, failed): (Vec<String>, Vec<String>) = summary
+        .files
+        .drain(..)
+        .partition(|path| std::fs::remove_file(path).is_ok());
+    summary.files = deleted;
+    summary.failed = failed;
+    Ok(summary)
+}
+
+/// Compares the DB with what is actually on disk.
+#[tauri::command]
+pub fn check_integrity(state: State<'_, AppState>) -> Result<IntegrityReport, String> {
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    integrity::check(wd, conn).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn repair_integrity(options: RepairOptions, state: State<'_, AppState>) -> Result<RepairReport, String> {
+    ensure_writable(&state)?;
+
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    // Relinks, status changes and removals land together or not at all
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let report = integrity::repair(wd, &tx, &options)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Applies the folder's retention policy to backups of `done` clips.
+/// With `dry_run` only lists what would be removed and how much it frees.
+#[tauri::command]
+pub fn clean_backups(dry_run: bool, state: State<'_, AppState>) -> Result<CleanupPlan, String> {
+    if !dry_run {
+        ensure_writable(&state)?;
+    }
+
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+
+    let mut plan = storage::plan_cleanup(wd, conn).map_err(|e| e.to_string())?;
+    if !dry_run {
+        storage::apply_cleanup(wd, conn, &mut plan).map_err(|e| e.to_string())?;
+    }
+    Ok(plan)
+}
+
 #[tauri::command]
 pub fn get_segments(clip_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
     let db_guard = state.db.lock().unwrap();
@@ -102,49 +658,224 @@ pub fn add_segment(
     clip_id: String,
     start_ms: f64,
     end_ms: f64,
+    overlap_policy: Option<OverlapPolicy>,
     state: State<'_, AppState>
 ) -> Result<Segment, String> {
+    ensure_writable(&state)?;
+
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let clip = db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+    let current = db::get_segments(conn, &clip_id).map_err(|e| e.to_string())?;
     
-    let existing = db::get_segments(conn, &clip_id).unwrap_or_default();
-    let idx = existing.len() as i64;
+    let idx = db::next_segment_idx(conn, &clip_id).map_err(|e| e.to_string())?;
     
-    let segment = Segment {
+    let candidate = Segment {
         id: Uuid::new_v4().to_string(),
         clip_id,
         idx,
         start_ms: start_ms.round() as i64,
         end_ms: end_ms.round() as i64,
         label: None,
+        export_number: None,
     };
+    let placed = segments::place(&clip, &current, candidate.clone(), overlap_policy.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    let segment = placed.iter().find(|s| s.id == candidate.id).cloned().ok_or("Segment not found")?;
     
-    db::insert_segment(conn, &segment).map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    if others_unchanged(&current, &placed, &segment.id) {
+        db::insert_segment(&tx, &segment).map_err(|e| e.to_string())?;
+        history::record(&tx, &segment.clip_id, &SegmentEdit::Add { segment: segment.clone() })?;
+    } else {
+        db::replace_segments(&tx, &segment.clip_id, &placed).map_err(|e| e.to_string())?;
+        history::record(&tx, &segment.clip_id, &SegmentEdit::Replace { before: current, after: placed })?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
     eprintln!("[add_segment] clip_id={}, start_ms={}, end_ms={}, segment_id={}", segment.clip_id, start_ms, end_ms, segment.id);
     Ok(segment)
 }
 
+/// Deletes a segment and closes the gap in its clip's indices. Returns the
+/// clip's remaining segments.
 #[tauri::command]
-pub fn delete_segment(segment_id: String, state: State<'_, AppState>) -> Result<(), String> {
+pub fn delete_segment(segment_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
-    db::delete_segment(conn, &segment_id).map_err(|e| e.to_string())
+    let segment = db::get_segment(conn, &segment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Segment not found")?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    db::delete_segment(&tx, &segment_id).map_err(|e| e.to_string())?;
+    db::renumber_segments(&tx, &segment.clip_id).map_err(|e| e.to_string())?;
+    let clip_id = segment.clip_id.clone();
+    history::record(&tx, &clip_id, &SegmentEdit::Delete { segment })?;
+    tx.commit().map_err(|e| e.to_string())?;
+    db::get_segments(conn, &clip_id).map_err(|e| e.to_string())
+}
+
+/// Puts a clip's segments in the given order. `ordered_ids` must list every
+/// segment of the clip exactly once.
+#[tauri::command]
+pub fn reorder_segments(clip_id: String, ordered_ids: Vec<String>, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let current: Vec<String> = db::get_segments(conn, &clip_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if !segments::is_reordering(&current, &ordered_ids) {
+        return Err("The new order must list each of the clip's segments exactly once".to_string());
+    }
+    set_segment_order(conn, &clip_id, current, ordered_ids)
+}
+
+/// Orders a clip's segments by where they start in the clip.
+#[tauri::command]
+pub fn sort_segments_by_start(clip_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let segments = db::get_segments(conn, &clip_id).map_err(|e| e.to_string())?;
+    let current: Vec<String> = segments.iter().map(|s| s.id.clone()).collect();
+    let ordered_ids = segments::order_by_start(&segments);
+    set_segment_order(conn, &clip_id, current, ordered_ids)
+}
+
+/// Applies and logs a new order unless it is the current one.
+fn set_segment_order(
+    conn: &rusqlite::Connection,
+    clip_id: &str,
+    current: Vec<String>,
+    ordered_ids: Vec<String>,
+) -> Result<Vec<Segment>, String> {
+    if ordered_ids != current {
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        db::set_segment_order(&tx, clip_id, &ordered_ids).map_err(|e| e.to_string())?;
+        history::record(&tx, clip_id, &SegmentEdit::Reorder { before: current, after: ordered_ids })?;
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    db::get_segments(conn, clip_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn update_segment_label(segment_id: String, label: String, state: State<'_, AppState>) -> Result<(), String> {
+    ensure_writable(&state)?;
+
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
-    conn.execute("UPDATE segments SET label = ?1 WHERE id = ?2", rusqlite::params![label, segment_id]).map_err(|e| e.to_string())?;
-    Ok(())
+    let segment = db::get_segment(conn, &segment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Segment not found")?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    db::update_segment_label(&tx, &segment_id, Some(&label)).map_err(|e| e.to_string())?;
+    history::record(&tx, &segment.clip_id, &SegmentEdit::UpdateLabel {
+        segment_id: segment_id.clone(),
+        before: segment.label,
+        after: Some(label),
+    })?;
+    tx.commit().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn update_segment_bounds(segment_id: String, start_ms: f64, end_ms: f64, state: State<'_, AppState>) -> Result<(), String> {
+pub fn update_segment_bounds(
+    segment_id: String,
+    start_ms: f64,
+    end_ms: f64,
+    overlap_policy: Option<OverlapPolicy>,
+    state: State<'_, AppState>,
+) -> Result<Segment, String> {
+    ensure_writable(&state)?;
+
     let db_guard = state.db.lock().unwrap();
     let conn = db_guard.as_ref().ok_or("DB not initialized")?;
-    conn.execute("UPDATE segments SET start_ms = ?1, end_ms = ?2 WHERE id = ?3", rusqlite::params![start_ms.round() as i64, end_ms.round() as i64, segment_id]).map_err(|e| e.to_string())?;
-    Ok(())
+    let segment = db::get_segment(conn, &segment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Segment not found")?;
+    let clip = db::get_clip_by_id(conn, &segment.clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+    let current = db::get_segments(conn, &clip.id).map_err(|e| e.to_string())?;
+
+    let before = Bounds { start_ms: segment.start_ms, end_ms: segment.end_ms };
+    let after = Bounds { start_ms: start_ms.round() as i64, end_ms: end_ms.round() as i64 };
+    let candidate = Segment { start_ms: after.start_ms, end_ms: after.end_ms, ..segment };
+    let placed = segments::place(&clip, &current, candidate, overlap_policy.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    let updated = placed.iter().find(|s| s.id == segment_id).cloned().ok_or("Segment not found")?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    if others_unchanged(&current, &placed, &segment_id) {
+        db::update_segment_bounds(&tx, &segment_id, after.start_ms, after.end_ms).map_err(|e| e.to_string())?;
+        history::record(&tx, &clip.id, &SegmentEdit::UpdateBounds { segment_id, before, after })?;
+    } else {
+        db::replace_segments(&tx, &clip.id, &placed).map_err(|e| e.to_string())?;
+        history::record(&tx, &clip.id, &SegmentEdit::Replace { before: current, after: placed })?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// Whether placing segment `id` left the rest of the list as it was, so the
+/// edit can be stored as a plain add or bounds change.
+fn others_unchanged(current: &[Segment], placed: &[Segment], id: &str) -> bool {
+    current.len() + usize::from(!current.iter().any(|s| s.id == id)) == placed.len()
+        && current.iter().filter(|s| s.id != id).all(|s| placed.contains(s))
+}
+
+/// Repairs a clip's segment list: reversed bounds are swapped, segments are
+/// clamped to the clip, ones shorter than a frame dropped and overlapping
+/// ones merged. Returns the segments.
+#[tauri::command]
+pub fn normalize_segments(clip_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let clip = db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+    let current = db::get_segments(conn, &clip_id).map_err(|e| e.to_string())?;
+    let normalized = segments::normalize(&clip, current.clone());
+
+    if normalized != current {
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        db::replace_segments(&tx, &clip_id, &normalized).map_err(|e| e.to_string())?;
+        history::record(&tx, &clip_id, &SegmentEdit::Replace { before: current, after: normalized })?;
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    db::get_segments(conn, &clip_id).map_err(|e| e.to_string())
+}
+
+/// Reverts the clip's last segment edit and returns its segments. The log is
+/// kept in the database, so this works across restarts.
+#[tauri::command]
+pub fn undo(clip_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    history::undo(&tx, &clip_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    db::get_segments(conn, &clip_id).map_err(|e| e.to_string())
+}
+
+/// Reapplies the segment edit last reverted by `undo`.
+#[tauri::command]
+pub fn redo(clip_id: String, state: State<'_, AppState>) -> Result<Vec<Segment>, String> {
+    ensure_writable(&state)?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    history::redo(&tx, &clip_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    db::get_segments(conn, &clip_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -154,10 +885,188 @@ pub async fn get_video_info(app_handle: tauri::AppHandle, path: String) -> Resul
 
 #[tauri::command]
 pub async fn extract_frame(app_handle: tauri::AppHandle, path: String, time_ms: i64) -> Result<String, String> {
-    let bytes = ffmpeg::extract_frame(&app_handle, &PathBuf::from(path), time_ms).await.map_err(|e| e.to_string())?;
+    let bytes = ffmpeg::extract_frame(&app_handle, &PathBuf::from(path), time_ms, None, FrameFormat::Jpg).await.map_err(|e| e.to_string())?;
     Ok(format!("data:image/jpeg;base64,{}", BASE64.encode(&bytes)))
 }
 
+/// Saves a still image of the clip at `time_ms` into OUT and returns its path.
+/// Uses the same cached pipeline as the media server's `/frame` endpoint.
+#[tauri::command]
+pub async fn export_frame(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    time_ms: i64,
+    format: Option<FrameFormat>,
+    state: State<'_, AppState>,
+    frames: State<'_, FrameCache>,
+) -> Result<String, String> {
+    ensure_writable(&state)?;
+
+    let (wd, clip) = {
+        let wd_guard = state.work_dir.lock().unwrap();
+        let wd = wd_guard.as_ref().ok_or("Working folder not set")?.clone();
+
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        let clip = db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?;
+        (wd, clip)
+    };
+    let format = format.unwrap_or(FrameFormat::Png);
+
+    let bytes = frames
+        .render(&app_handle, &clip.id, &PathBuf::from(&clip.backup_path), time_ms, None, format)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let base_name = PathBuf::from(&clip.original_name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let out_name = format!("{}__frame{}", base_name, time_ms);
+    let out_dir = wd.out_dir_for(&clip.relative_path).map_err(|e| e.to_string())?;
+    let mut target_out = out_dir.join(format!("{}.{}", out_name, format.extension()));
+    let mut counter = 2;
+    while target_out.exists() {
+        target_out = out_dir.join(format!("{}_v{}.{}", out_name, counter, format.extension()));
+        counter += 1;
+    }
+
+    std::fs::write(&target_out, bytes.as_slice()).map_err(|e| e.to_string())?;
+    record_export(&app_handle, &clip.id, &target_out);
+    Ok(target_out.to_string_lossy().to_string())
+}
+
+/// Remembers an output file so removing the clip can offer to delete it.
+fn record_export(app_handle: &tauri::AppHandle, clip_id: &str, path: &std::path::Path) {
+    use tauri::Manager;
+    let state = app_handle.state::<AppState>();
+    let db_guard = state.db.lock().unwrap();
+    if let Some(conn) = db_guard.as_ref() {
+        if let Err(e) = db::insert_export(conn, clip_id, &path.to_string_lossy()) {
+            eprintln!("[export] could not record {:?}: {}", path, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn detect_audio_highlights(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    options: Option<HighlightOptions>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SuggestedSegment>, String> {
+    let clip = {
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?
+    };
+    let path = PathBuf::from(&clip.backup_path);
+    let options = options.unwrap_or_default();
+
+    let probe = ffmpeg::probe_media(&app_handle, &path).await.map_err(|e| e.to_string())?;
+    let samples = ffmpeg::measure_loudness(&app_handle, &path, &probe).await.map_err(|e| e.to_string())?;
+    let duration_ms = (probe.duration_sec * 1000.0).round() as i64;
+
+    let highlights = detect::find_audio_highlights(&samples, duration_ms, &options);
+    eprintln!("[highlights] clip_id={}, samples={}, highlights={}", clip_id, samples.len(), highlights.len());
+    Ok(highlights)
+}
+
+#[derive(Clone, serde::Serialize)]
+struct DetectJobPayload {
+    job_id: String,
+    clip_id: String,
+    clip_name: String,
+    kind: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct DetectJobProgressPayload {
+    job_id: String,
+    percent: u32,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct DetectJobCompletedPayload {
+    job_id: String,
+    clip_id: String,
+    segments: Vec<SuggestedSegment>,
+}
+
+/// Starts motion detection in the background and returns the job id.
+/// Results arrive through `detect-job-completed`.
+#[tauri::command]
+pub async fn detect_motion(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    options: Option<MotionOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let clip = {
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        db::get_clip_by_id(conn, &clip_id).map_err(|e| e.to_string())?.ok_or("Clip not found")?
+    };
+    let options = options.unwrap_or_default();
+    let job_id = Uuid::new_v4().to_string();
+
+    use tauri::Emitter;
+    let _ = app_handle.emit("detect-job-started", DetectJobPayload {
+        job_id: job_id.clone(),
+        clip_id: clip_id.clone(),
+        clip_name: clip.original_name.clone(),
+        kind: "motion".to_string(),
+    });
+
+    let returned_job_id = job_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let path = PathBuf::from(&clip.backup_path);
+        let result = async {
+            let info = ffmpeg::get_video_info(&app_handle, &path).await?;
+            let duration_ms = (info.duration_sec * 1000.0).round() as i64;
+
+            let mut last_percent = 0;
+            let samples = ffmpeg::measure_motion(&app_handle, &path, |time_ms| {
+                if duration_ms <= 0 {
+                    return;
+                }
+                let percent = ((time_ms * 100) / duration_ms).clamp(0, 100) as u32;
+                if percent > last_percent {
+                    last_percent = percent;
+                    let _ = app_handle.emit("detect-job-progress", DetectJobProgressPayload {
+                        job_id: job_id.clone(),
+                        percent,
+                    });
+                }
+            })
+            .await?;
+
+            Ok::<_, std::io::Error>(detect::find_motion_periods(&samples, duration_ms, &options))
+        }
+        .await;
+
+        match result {
+            Ok(segments) => {
+                eprintln!("[motion] clip_id={}, periods={}", clip_id, segments.len());
+                let _ = app_handle.emit("detect-job-completed", DetectJobCompletedPayload {
+                    job_id,
+                    clip_id,
+                    segments,
+                });
+            }
+            Err(e) => {
+                let _ = app_handle.emit("detect-job-failed", JobFailedPayload {
+                    job_id,
+                    error: e.to_string(),
+                });
+            }
+        }
+    });
+
+    Ok(returned_job_id)
+}
+
 #[derive(Clone, serde::Serialize)]
 struct JobPayload {
     job_id: String,
@@ -185,7 +1094,14 @@ struct JobFailedPayload {
 }
 
 #[tauri::command]
-pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn export_segments(
+    app_handle: tauri::AppHandle,
+    clip_id: String,
+    stream_copy: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    ensure_writable(&state)?;
+
     let (wd, clip, segments) = {
         let wd_guard = state.work_dir.lock().unwrap();
         let wd = wd_guard.as_ref().ok_or("Working folder not set")?.clone();
@@ -195,15 +1111,40 @@ pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, stat
         
         let clips = db::get_clips(conn).map_err(|e| e.to_string())?;
         let clip = clips.into_iter().find(|c| c.id == clip_id.clone()).ok_or("Clip not found")?;
+        if clip.status.is_exporting() {
+            return Err(ALREADY_EXPORTING_ERROR.to_string());
+        }
         
         let segments = db::get_segments(conn, &clip_id).map_err(|e| e.to_string())?;
-        eprintln!("[export] clip_id={}, backup_path={}, segment_count={}", clip_id, clip.backup_path, segments.len());
+        if segments.is_empty() {
+            return Err("No segments to export. Add segments before exporting.".to_string());
+        }
         (wd, clip, segments)
     };
 
-    if segments.is_empty() {
-        return Err("No segments to export. Add segments before exporting.".to_string());
-    }
+    // Refuse up front rather than failing halfway with a full drive
+    let source = PathBuf::from(&clip.backup_path);
+    let source_bytes = std::fs::metadata(&source).map_err(|e| e.to_string())?.len();
+    let probe = ffmpeg::probe_media(&app_handle, &source).await.map_err(|e| e.to_string())?;
+    let needed = storage::estimate_export_bytes(source_bytes, probe.duration_sec, &segments);
+    let out_dir = wd.out_dir_for(&clip.relative_path).map_err(|e| e.to_string())?;
+    storage::ensure_free_space(&out_dir, needed)?;
+
+    // Numbers are fixed only for an export that goes ahead, so outputs keep
+    // their names on re-export
+    let segments = {
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        // Another export may have claimed the clip since it was read
+        let from = db::update_clip_status(&tx, &clip_id, ClipStatus::Queued).map_err(|e| e.to_string())?;
+        if from.is_exporting() {
+            return Err(ALREADY_EXPORTING_ERROR.to_string());
+        }
+        let segments = db::assign_export_numbers(&tx, &clip_id).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        segments
+    };
     
     // Generate a unique job ID for this export
     let job_id = uuid::Uuid::new_v4().to_string();
@@ -221,25 +1162,45 @@ pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, stat
 
     // Spawn detached task for background export
     tauri::async_runtime::spawn(async move {
+        let _slot = EXPORT_SLOT.lock().await;
+        // The clip may have been reopened or reset while it waited
+        if let Err(e) = set_export_status(&app_handle, &clip_id, ClipStatus::Exporting) {
+            let _ = app_handle.emit("export-job-failed", JobFailedPayload {
+                job_id: job_id.clone(),
+                error: format!("Export cancelled: {}", e),
+            });
+            return;
+        }
+
         let base_name = PathBuf::from(&clip.original_name)
             .file_stem()
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
+        let container = OutputContainer::for_clip(
+            &PathBuf::from(&clip.original_name),
+            clip.video_codec.as_deref(),
+            clip.audio_codec.as_deref(),
+        );
+        // Copying needs codecs the container takes; anything else is re-encoded
+        let stream_copy = stream_copy.unwrap_or(false)
+            && clip.video_codec.as_deref().is_some_and(|v| container.accepts(v, clip.audio_codec.as_deref()));
             
         for (i, seg) in segments.iter().enumerate() {
             let seg_num = i + 1;
-            let mut out_name = format!("{}__trim{:02}", base_name, seg_num);
+            let export_number = seg.export_number.unwrap_or(seg_num as i64);
+            let mut out_name = format!("{}__trim{:02}", base_name, export_number);
             if let Some(label) = &seg.label {
                 if !label.trim().is_empty() {
                     out_name = format!("{}__{}", out_name, label);
                 }
             }
             
-            let mut target_out = wd.out_dir.join(format!("{}.mp4", out_name));
+            let ext = container.extension();
+            let mut target_out = out_dir.join(format!("{}.{}", out_name, ext));
             let mut counter = 2;
             while target_out.exists() {
-                target_out = wd.out_dir.join(format!("{}_v{}.mp4", out_name, counter));
+                target_out = out_dir.join(format!("{}_v{}.{}", out_name, counter, ext));
                 counter += 1;
             }
             
@@ -249,9 +1210,12 @@ pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, stat
                 &PathBuf::from(&clip.backup_path),
                 &target_out,
                 seg.start_ms,
-                seg.end_ms
+                seg.end_ms,
+                container,
+                stream_copy,
             ).await {
                 Ok(_) => {
+                    record_export(&app_handle, &clip_id, &target_out);
                     // Update progress
                     let _ = app_handle.emit("export-job-progress", JobProgressPayload {
                         job_id: job_id.clone(),
@@ -261,6 +1225,7 @@ pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, stat
                 }
                 Err(e) => {
                     // Emit error and abort this job
+                    finish_export(&app_handle, &clip_id, ClipStatus::Failed);
                     let _ = app_handle.emit("export-job-failed", JobFailedPayload {
                         job_id: job_id.clone(),
                         error: e.to_string(),
@@ -270,20 +1235,38 @@ pub async fn export_segments(app_handle: tauri::AppHandle, clip_id: String, stat
             }
         }
         
-        // Finalize
-        // Since we are in an async detached thread, we cannot easily access Tauri State. 
-        // We'll just open a fresh DB connection to update status, as we have the DB path in WorkDir.
-        let conn = rusqlite::Connection::open(&wd.db_path).ok();
-        if let Some(c) = conn {
-             let _ = db::update_clip_status(&c, &clip_id, "done");
-        }
-
+        finish_export(&app_handle, &clip_id, ClipStatus::Done);
         let _ = app_handle.emit("export-job-completed", JobCompletedPayload { job_id });
     });
     
     Ok(())
 }
 
+/// Changes a clip's status from the export task, which goes through the
+/// shared connection rather than opening a second one.
+fn set_export_status(app_handle: &tauri::AppHandle, clip_id: &str, status: ClipStatus) -> Result<(), String> {
+    use tauri::Manager;
+    let state = app_handle.state::<AppState>();
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+    change_clip_status(conn, clip_id, status).map(|_| ())
+}
+
+/// Moves a clip out of `exporting`.
+fn finish_export(app_handle: &tauri::AppHandle, clip_id: &str, status: ClipStatus) {
+    if let Err(e) = set_export_status(app_handle, clip_id, status) {
+        eprintln!("[export] could not mark clip {} as {}: {}", clip_id, status, e);
+    }
+}
+
+#[tauri::command]
+pub fn get_media_server_info(server: State<'_, MediaServerState>) -> Result<MediaServerInfo, String> {
+    match server.inner() {
+        MediaServerState::Running(info) => Ok(info.clone()),
+        MediaServerState::Failed(error) => Err(error.clone()),
+    }
+}
+
 #[tauri::command]
 pub async fn test_ffmpeg(app_handle: tauri::AppHandle) -> Result<String, String> {
     use tauri_plugin_shell::ShellExt;
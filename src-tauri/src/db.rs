@@ -1,17 +1,266 @@
-use rusqlite::{params, Connection, Result};
+use crate::migrations::{self, MigrationError};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result, ToSql};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::path::Path;
+use std::time::Duration;
+
+/// Where a clip is in the review workflow. Stored as its snake_case name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipStatus {
+    /// Ingested from IN, not opened yet.
+    New,
+    /// Opened; the original is secured in BACKUP.
+    InProgress,
+    /// Waiting for an export to start.
+    Queued,
+    Exporting,
+    Done,
+    /// The last export failed.
+    Failed,
+    /// Set aside by the user for later.
+    Skipped,
+    /// Not worth keeping.
+    Rejected,
+    /// Its original is no longer where the DB says.
+    Missing,
+}
+
+impl ClipStatus {
+    const ALL: [Self; 9] = [
+        Self::New,
+        Self::InProgress,
+        Self::Queued,
+        Self::Exporting,
+        Self::Done,
+        Self::Failed,
+        Self::Skipped,
+        Self::Rejected,
+        Self::Missing,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::InProgress => "in_progress",
+            Self::Queued => "queued",
+            Self::Exporting => "exporting",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+            Self::Rejected => "rejected",
+            Self::Missing => "missing",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+
+    /// States the user may pick by hand. The rest are set by exports and
+    /// integrity checks only.
+    pub fn is_user_settable(self) -> bool {
+        matches!(self, Self::New | Self::InProgress | Self::Skipped | Self::Rejected)
+    }
+
+    /// Whether an export job owns the clip.
+    pub fn is_exporting(self) -> bool {
+        matches!(self, Self::Queued | Self::Exporting)
+    }
+
+    /// The workflow. Any state may become `missing`, and staying in the same
+    /// state is always allowed.
+    pub fn can_transition_to(self, to: Self) -> bool {
+        use ClipStatus::*;
+        if self == to || to == Missing {
+            return true;
+        }
+        match self {
+            New => matches!(to, InProgress | Skipped | Rejected),
+            // Back to `new` when the original is restored to IN
+            InProgress => matches!(to, New | Queued | Exporting | Done | Skipped | Rejected),
+            Queued => matches!(to, Exporting | InProgress | Failed),
+            Exporting => matches!(to, Done | Failed),
+            Done => matches!(to, New | InProgress | Queued | Exporting),
+            Failed => matches!(to, New | InProgress | Queued | Exporting),
+            Skipped | Rejected => matches!(to, New | InProgress),
+            // Relinked, either still waiting in IN or found in BACKUP
+            Missing => matches!(to, New | InProgress),
+        }
+    }
+}
+
+impl fmt::Display for ClipStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql for ClipStatus {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ClipStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Self::parse(text).ok_or_else(|| FromSqlError::Other(format!("unknown clip status {:?}", text).into()))
+    }
+}
+
+/// Whether a clip has, or needs, a lightweight preview proxy. Stored as its
+/// snake_case name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyStatus {
+    /// Not probed yet.
+    Unchecked,
+    /// The webview plays the original well enough.
+    NotNeeded,
+    /// Needed, but waits until the clip is opened.
+    Pending,
+    Generating,
+    Ready,
+    Failed,
+}
+
+impl ProxyStatus {
+    const ALL: [Self; 6] = [
+        Self::Unchecked,
+        Self::NotNeeded,
+        Self::Pending,
+        Self::Generating,
+        Self::Ready,
+        Self::Failed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unchecked => "unchecked",
+            Self::NotNeeded => "not_needed",
+            Self::Pending => "pending",
+            Self::Generating => "generating",
+            Self::Ready => "ready",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+}
+
+impl ToSql for ProxyStatus {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ProxyStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Self::parse(text).ok_or_else(|| FromSqlError::Other(format!("unknown proxy status {:?}", text).into()))
+    }
+}
+
+/// Why a status change was refused.
+#[derive(Debug)]
+pub enum StatusError {
+    NotFound(String),
+    IllegalTransition { from: ClipStatus, to: ClipStatus },
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(id) => write!(f, "Clip {} not found", id),
+            Self::IllegalTransition { from, to } => {
+                write!(f, "A clip that is {} cannot be marked {}", from, to)
+            }
+            Self::Sqlite(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StatusError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+/// One entry of a clip's status history. `from` is `None` for ingest.
+#[derive(Debug, Serialize)]
+pub struct StatusChange {
+    pub from: Option<ClipStatus>,
+    pub to: ClipStatus,
+    pub changed_at: i64,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Clip {
     pub id: String,
     pub original_name: String,
+    /// Location inside IN with `/` separators, e.g. `day1/clip.mp4`. Unique per clip.
+    pub relative_path: String,
     pub backup_path: String,
-    pub status: String,
+    pub status: ClipStatus,
     pub created_at: i64,
+    pub proxy_status: ProxyStatus,
+    pub proxy_path: Option<String>,
+    /// ffprobe `format_name`, e.g. `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`.
+    pub container: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    /// File size in bytes at ingest.
+    pub size_bytes: Option<i64>,
+    /// Quick content fingerprint (size plus head/middle/tail samples).
+    pub fingerprint: Option<String>,
+    /// Full BLAKE3 of the file, filled in by a background pass after ingest.
+    pub content_hash: Option<String>,
+    /// Probed at ingest, or when first opened for older clips.
+    pub duration_ms: Option<i64>,
+    pub fps: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Clip {
+    /// Length of one frame, rounded up; 1 ms if the frame rate is unknown.
+    pub fn frame_ms(&self) -> i64 {
+        self.fps
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| (1000.0 / fps).ceil() as i64)
+            .unwrap_or(1)
+    }
+}
+
+const CLIP_COLUMNS: &str = "id, original_name, relative_path, backup_path, status, created_at, proxy_status, proxy_path, container, video_codec, audio_codec, size_bytes, fingerprint, content_hash, duration_ms, fps";
+
+fn clip_from_row(row: &rusqlite::Row) -> Result<Clip> {
+    Ok(Clip {
+        id: row.get(0)?,
+        original_name: row.get(1)?,
+        relative_path: row.get(2)?,
+        backup_path: row.get(3)?,
+        status: row.get(4)?,
+        created_at: row.get(5)?,
+        proxy_status: row.get(6)?,
+        proxy_path: row.get(7)?,
+        container: row.get(8)?,
+        video_codec: row.get(9)?,
+        audio_codec: row.get(10)?,
+        size_bytes: row.get(11)?,
+        fingerprint: row.get(12)?,
+        content_hash: row.get(13)?,
+        duration_ms: row.get(14)?,
+        fps: row.get(15)?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Segment {
     pub id: String,
     pub clip_id: String,
@@ -19,64 +268,82 @@ pub struct Segment {
     pub start_ms: i64,
     pub end_ms: i64,
     pub label: Option<String>,
+    /// The `__trimNN` number of the segment's outputs, fixed by its first
+    /// export so reordering or deleting others does not rename them.
+    pub export_number: Option<i64>,
 }
 
-pub fn init_db<P: AsRef<Path>>(db_path: P) -> Result<Connection> {
-    let conn = Connection::open(db_path)?;
+/// Opens the database, creating or upgrading its schema as needed.
+pub fn init_db<P: AsRef<Path>>(db_path: P) -> std::result::Result<Connection, MigrationError> {
+    let mut conn = Connection::open(&db_path)?;
+    configure(&conn)?;
+    migrations::migrate(&mut conn, db_path.as_ref())?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS clips (
-            id TEXT PRIMARY KEY,
-            original_name TEXT NOT NULL,
-            backup_path TEXT NOT NULL,
-            status TEXT NOT NULL,
-            created_at INTEGER NOT NULL
-        )",
-        [],
-    )?;
+    // Older versions never enabled foreign keys, so deletes did not cascade
+    let orphaned = delete_orphaned_segments(&conn)?;
+    conn.execute("DELETE FROM exports WHERE clip_id NOT IN (SELECT id FROM clips)", [])?;
+    if orphaned > 0 {
+        eprintln!("[db] removed {} orphaned segments", orphaned);
+    }
+    Ok(conn)
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS segments (
-            id TEXT PRIMARY KEY,
-            clip_id TEXT NOT NULL,
-            idx INTEGER NOT NULL,
-            start_ms INTEGER NOT NULL,
-            end_ms INTEGER NOT NULL,
-            label TEXT,
-            FOREIGN KEY(clip_id) REFERENCES clips(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
+/// Settings for the writing connection: the shared ones below, plus WAL so
+/// readers do not block the writer.
+fn configure(conn: &Connection) -> Result<()> {
+    configure_session(conn)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    Ok(())
+}
+
+/// Settings every connection needs, read-only ones included: enforce
+/// foreign keys (off by default in SQLite) and wait on a busy database
+/// instead of failing immediately. Nothing here writes to the file.
+fn configure_session(conn: &Connection) -> Result<()> {
+    conn.pragma_update(None, "foreign_keys", true)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    Ok(())
+}
 
+/// Opens an existing database without writing to it, for folders opened
+/// read-only while another instance holds the lock.
+pub fn open_read_only<P: AsRef<Path>>(db_path: P) -> std::result::Result<Connection, MigrationError> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    configure_session(&conn)?;
+    migrations::check_version(&conn)?;
     Ok(conn)
 }
 
 pub fn insert_clip(conn: &Connection, clip: &Clip) -> Result<()> {
     conn.execute(
-        "INSERT INTO clips (id, original_name, backup_path, status, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO clips (id, original_name, relative_path, backup_path, status, created_at, proxy_status, proxy_path, container, video_codec, audio_codec, size_bytes, fingerprint, content_hash, duration_ms, fps)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
         params![
             clip.id,
             clip.original_name,
+            clip.relative_path,
             clip.backup_path,
             clip.status,
-            clip.created_at
+            clip.created_at,
+            clip.proxy_status,
+            clip.proxy_path,
+            clip.container,
+            clip.video_codec,
+            clip.audio_codec,
+            clip.size_bytes,
+            clip.fingerprint,
+            clip.content_hash,
+            clip.duration_ms,
+            clip.fps
         ],
     )?;
+    record_status_change(conn, &clip.id, None, clip.status)?;
     Ok(())
 }
 
 pub fn get_clips(conn: &Connection) -> Result<Vec<Clip>> {
-    let mut stmt = conn.prepare("SELECT id, original_name, backup_path, status, created_at FROM clips ORDER BY created_at DESC")?;
-    let clip_iter = stmt.query_map([], |row| {
-        Ok(Clip {
-            id: row.get(0)?,
-            original_name: row.get(1)?,
-            backup_path: row.get(2)?,
-            status: row.get(3)?,
-            created_at: row.get(4)?,
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM clips ORDER BY created_at DESC", CLIP_COLUMNS))?;
+    let clip_iter = stmt.query_map([], clip_from_row)?;
 
     let mut clips = Vec::new();
     for clip in clip_iter {
@@ -85,42 +352,242 @@ pub fn get_clips(conn: &Connection) -> Result<Vec<Clip>> {
     Ok(clips)
 }
 
-pub fn update_clip_status(conn: &Connection, id: &str, status: &str) -> Result<()> {
+pub fn count_clips(conn: &Connection) -> Result<usize> {
+    conn.query_row("SELECT COUNT(*) FROM clips", [], |row| row.get::<_, i64>(0))
+        .map(|count| count as usize)
+}
+
+/// Number of clips per status, e.g. `{"done": 3, "new": 5}`.
+pub fn count_clips_by_status(conn: &Connection) -> Result<BTreeMap<String, usize>> {
+    let mut stmt = conn.prepare("SELECT status, COUNT(*) FROM clips GROUP BY status")?;
+    let count_iter = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?;
+    count_iter.collect()
+}
+
+pub fn get_clip_by_id(conn: &Connection, id: &str) -> Result<Option<Clip>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM clips WHERE id = ?1", CLIP_COLUMNS))?;
+    let mut clip_iter = stmt.query_map(params![id], clip_from_row)?;
+
+    if let Some(res) = clip_iter.next() {
+        Ok(Some(res?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Moves a clip to `to` if the workflow allows it and records the change.
+/// Returns the previous status.
+pub fn update_clip_status(conn: &Connection, id: &str, to: ClipStatus) -> std::result::Result<ClipStatus, StatusError> {
+    let from: ClipStatus = conn
+        .query_row("SELECT status FROM clips WHERE id = ?1", params![id], |row| row.get(0))
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => StatusError::NotFound(id.to_string()),
+            e => StatusError::Sqlite(e),
+        })?;
+    if from == to {
+        return Ok(from);
+    }
+    if !from.can_transition_to(to) {
+        return Err(StatusError::IllegalTransition { from, to });
+    }
+    conn.execute("UPDATE clips SET status = ?1 WHERE id = ?2", params![to, id])?;
+    record_status_change(conn, id, Some(from), to)?;
+    Ok(from)
+}
+
+fn record_status_change(conn: &Connection, id: &str, from: Option<ClipStatus>, to: ClipStatus) -> Result<()> {
+    conn.execute(
+        "INSERT INTO clip_status_history (clip_id, from_status, to_status, changed_at)
+         VALUES (?1, ?2, ?3, strftime('%s', 'now'))",
+        params![id, from, to],
+    )?;
+    Ok(())
+}
+
+/// Status changes of a clip, oldest first.
+/// When each clip last became `done`, by clip ID.
+pub fn get_done_times(conn: &Connection) -> Result<HashMap<String, i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT clip_id, MAX(changed_at) FROM clip_status_history WHERE to_status = 'done' GROUP BY clip_id",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+    rows.collect()
+}
+
+pub fn get_status_history(conn: &Connection, clip_id: &str) -> Result<Vec<StatusChange>> {
+    let mut stmt = conn.prepare(
+        "SELECT from_status, to_status, changed_at FROM clip_status_history WHERE clip_id = ?1 ORDER BY id",
+    )?;
+    let change_iter = stmt.query_map(params![clip_id], |row| {
+        Ok(StatusChange {
+            from: row.get(0)?,
+            to: row.get(1)?,
+            changed_at: row.get(2)?,
+        })
+    })?;
+    change_iter.collect()
+}
+
+pub fn get_clips_by_fingerprint(conn: &Connection, fingerprint: &str) -> Result<Vec<Clip>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM clips WHERE fingerprint = ?1", CLIP_COLUMNS))?;
+    let clip_iter = stmt.query_map(params![fingerprint], clip_from_row)?;
+    clip_iter.collect()
+}
+
+/// Clips whose quick fingerprint or full hash is still missing.
+pub fn get_clips_missing_hash(conn: &Connection) -> Result<Vec<Clip>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM clips WHERE fingerprint IS NULL OR content_hash IS NULL",
+        CLIP_COLUMNS
+    ))?;
+    let clip_iter = stmt.query_map([], clip_from_row)?;
+    clip_iter.collect()
+}
+
+/// Stores a clip's hashes. The size recorded at ingest is kept, so a file
+/// that changed since shows up as a size mismatch; it is only filled in for
+/// clips ingested before sizes were recorded.
+pub fn update_clip_hashes(
+    conn: &Connection,
+    id: &str,
+    size_bytes: i64,
+    fingerprint: &str,
+    content_hash: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE clips SET size_bytes = COALESCE(size_bytes, ?1), fingerprint = ?2, content_hash = COALESCE(?3, content_hash) WHERE id = ?4",
+        params![size_bytes, fingerprint, content_hash, id],
+    )?;
+    Ok(())
+}
+
+/// Points a clip at a new location inside IN after its file was renamed or moved.
+pub fn update_clip_location(
+    conn: &Connection,
+    id: &str,
+    original_name: &str,
+    relative_path: &str,
+    backup_path: &str,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE clips SET original_name = ?1, relative_path = ?2, backup_path = ?3 WHERE id = ?4",
+        params![original_name, relative_path, backup_path, id],
+    )?;
+    Ok(())
+}
+
+/// Deletes a clip together with its segments and export records.
+pub fn delete_clip(conn: &Connection, id: &str) -> Result<()> {
+    // Segments and exports follow via ON DELETE CASCADE
+    conn.execute("DELETE FROM clips WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Moves a clip back to `new` at a location inside IN.
+pub fn reset_clip_to_new(
+    conn: &Connection,
+    id: &str,
+    original_name: &str,
+    relative_path: &str,
+    backup_path: &str,
+) -> std::result::Result<(), StatusError> {
+    update_clip_status(conn, id, ClipStatus::New)?;
+    update_clip_location(conn, id, original_name, relative_path, backup_path)?;
+    Ok(())
+}
+
+/// Records a file written to OUT for a clip.
+pub fn insert_export(conn: &Connection, clip_id: &str, path: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO exports (clip_id, path, created_at) VALUES (?1, ?2, strftime('%s', 'now'))",
+        params![clip_id, path],
+    )?;
+    Ok(())
+}
+
+pub fn get_export_paths(conn: &Connection, clip_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT path FROM exports WHERE clip_id = ?1 ORDER BY id")?;
+    let path_iter = stmt.query_map(params![clip_id], |row| row.get(0))?;
+    path_iter.collect()
+}
+
+/// Every output path recorded for any clip.
+pub fn get_all_export_paths(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT path FROM exports")?;
+    let path_iter = stmt.query_map([], |row| row.get(0))?;
+    path_iter.collect()
+}
+
+pub fn update_clip_backup_path(conn: &Connection, id: &str, backup_path: &str) -> Result<()> {
     conn.execute(
-        "UPDATE clips SET status = ?1 WHERE id = ?2",
-        params![status, id],
+        "UPDATE clips SET backup_path = ?1 WHERE id = ?2",
+        params![backup_path, id],
+    )?;
+    Ok(())
+}
+
+/// Forgets the original of a clip whose backup was cleaned up.
+pub fn clear_clip_backup(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE clips SET backup_path = '', proxy_status = ?1, proxy_path = NULL WHERE id = ?2",
+        params![ProxyStatus::NotNeeded, id],
+    )?;
+    Ok(())
+}
+
+pub fn update_clip_timing(conn: &Connection, id: &str, duration_ms: i64, fps: Option<f64>) -> Result<()> {
+    conn.execute(
+        "UPDATE clips SET duration_ms = ?1, fps = ?2 WHERE id = ?3",
+        params![duration_ms, fps, id],
+    )?;
+    Ok(())
+}
+
+pub fn update_clip_proxy(conn: &Connection, id: &str, status: ProxyStatus, path: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE clips SET proxy_status = ?1, proxy_path = ?2 WHERE id = ?3",
+        params![status, path, id],
     )?;
     Ok(())
 }
 
 pub fn insert_segment(conn: &Connection, segment: &Segment) -> Result<()> {
     conn.execute(
-        "INSERT INTO segments (id, clip_id, idx, start_ms, end_ms, label)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO segments (id, clip_id, idx, start_ms, end_ms, label, export_number)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         params![
             segment.id,
             segment.clip_id,
             segment.idx,
             segment.start_ms,
             segment.end_ms,
-            segment.label
+            segment.label,
+            segment.export_number
         ],
     )?;
     Ok(())
 }
 
+const SEGMENT_COLUMNS: &str = "id, clip_id, idx, start_ms, end_ms, label, export_number";
+
+fn segment_from_row(row: &rusqlite::Row) -> Result<Segment> {
+    Ok(Segment {
+        id: row.get(0)?,
+        clip_id: row.get(1)?,
+        idx: row.get(2)?,
+        start_ms: row.get(3)?,
+        end_ms: row.get(4)?,
+        label: row.get(5)?,
+        export_number: row.get(6)?,
+    })
+}
+
 pub fn get_segments(conn: &Connection, clip_id: &str) -> Result<Vec<Segment>> {
-    let mut stmt = conn.prepare("SELECT id, clip_id, idx, start_ms, end_ms, label FROM segments WHERE clip_id = ?1 ORDER BY idx ASC")?;
-    let segment_iter = stmt.query_map(params![clip_id], |row| {
-        Ok(Segment {
-            id: row.get(0)?,
-            clip_id: row.get(1)?,
-            idx: row.get(2)?,
-            start_ms: row.get(3)?,
-            end_ms: row.get(4)?,
-            label: row.get(5)?,
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM segments WHERE clip_id = ?1 ORDER BY idx ASC, rowid ASC",
+        SEGMENT_COLUMNS
+    ))?;
+    let segment_iter = stmt.query_map(params![clip_id], segment_from_row)?;
 
     let mut segments = Vec::new();
     for split in segment_iter {
@@ -129,6 +596,65 @@ pub fn get_segments(conn: &Connection, clip_id: &str) -> Result<Vec<Segment>> {
     Ok(segments)
 }
 
+pub fn get_segment(conn: &Connection, id: &str) -> Result<Option<Segment>> {
+    conn.query_row(
+        &format!("SELECT {} FROM segments WHERE id = ?1", SEGMENT_COLUMNS),
+        params![id],
+        segment_from_row,
+    )
+    .optional()
+}
+
+/// Index for a segment appended to the end of the clip's list.
+pub fn next_segment_idx(conn: &Connection, clip_id: &str) -> Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(idx) + 1, 0) FROM segments WHERE clip_id = ?1",
+        params![clip_id],
+        |row| row.get(0),
+    )
+}
+
+/// Gives the clip's segments the indices `0..n` in `ordered_ids` order.
+/// The caller checks that the IDs are exactly the clip's segments.
+pub fn set_segment_order(conn: &Connection, clip_id: &str, ordered_ids: &[String]) -> Result<()> {
+    let mut stmt = conn.prepare("UPDATE segments SET idx = ?1 WHERE id = ?2 AND clip_id = ?3")?;
+    for (idx, id) in ordered_ids.iter().enumerate() {
+        stmt.execute(params![idx as i64, id, clip_id])?;
+    }
+    Ok(())
+}
+
+/// Swaps the clip's whole segment list for `segments`, indexed in order.
+pub fn replace_segments(conn: &Connection, clip_id: &str, segments: &[Segment]) -> Result<()> {
+    delete_segments_for_clip(conn, clip_id)?;
+    for (idx, segment) in segments.iter().enumerate() {
+        insert_segment(conn, &Segment { idx: idx as i64, ..segment.clone() })?;
+    }
+    Ok(())
+}
+
+/// Closes gaps and ties in the clip's indices, keeping the current order.
+pub fn renumber_segments(conn: &Connection, clip_id: &str) -> Result<()> {
+    let ids: Vec<String> = get_segments(conn, clip_id)?.into_iter().map(|s| s.id).collect();
+    set_segment_order(conn, clip_id, &ids)
+}
+
+/// Gives every segment of the clip that was never exported the next free
+/// export number, in list order, and returns the segments.
+pub fn assign_export_numbers(conn: &Connection, clip_id: &str) -> Result<Vec<Segment>> {
+    let mut segments = get_segments(conn, clip_id)?;
+    let first_free = segments.iter().filter_map(|s| s.export_number).max().unwrap_or(0) + 1;
+    let unnumbered = segments.iter_mut().filter(|s| s.export_number.is_none());
+    for (number, segment) in (first_free..).zip(unnumbered) {
+        conn.execute(
+            "UPDATE segments SET export_number = ?1 WHERE id = ?2",
+            params![number, segment.id],
+        )?;
+        segment.export_number = Some(number);
+    }
+    Ok(segments)
+}
+
 pub fn update_segment(
     conn: &Connection,
     id: &str,
@@ -143,22 +669,124 @@ pub fn update_segment(
     Ok(())
 }
 
+pub fn update_segment_bounds(conn: &Connection, id: &str, start_ms: i64, end_ms: i64) -> Result<usize> {
+    conn.execute(
+        "UPDATE segments SET start_ms = ?1, end_ms = ?2 WHERE id = ?3",
+        params![start_ms, end_ms, id],
+    )
+}
+
+pub fn update_segment_label(conn: &Connection, id: &str, label: Option<&str>) -> Result<usize> {
+    conn.execute("UPDATE segments SET label = ?1 WHERE id = ?2", params![label, id])
+}
+
+/// A row of the segment undo log. `before_value` and `after_value` are JSON
+/// whose shape depends on `kind`; see `history::SegmentEdit`.
+#[derive(Debug)]
+pub struct SegmentOperation {
+    pub id: i64,
+    pub kind: String,
+    pub segment_id: Option<String>,
+    pub before_value: Option<String>,
+    pub after_value: Option<String>,
+    pub created_at: i64,
+}
+
+const SEGMENT_OPERATION_COLUMNS: &str = "id, kind, segment_id, before_value, after_value, created_at";
+
+fn segment_operation_from_row(row: &rusqlite::Row) -> Result<SegmentOperation> {
+    Ok(SegmentOperation {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        segment_id: row.get(2)?,
+        before_value: row.get(3)?,
+        after_value: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+pub fn insert_segment_operation(conn: &Connection, clip_id: &str, op: &SegmentOperation) -> Result<()> {
+    conn.execute(
+        "INSERT INTO segment_operations (clip_id, kind, segment_id, before_value, after_value, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![clip_id, op.kind, op.segment_id, op.before_value, op.after_value, op.created_at],
+    )?;
+    Ok(())
+}
+
+/// The operation `undo` would revert next.
+pub fn get_last_applied_segment_operation(conn: &Connection, clip_id: &str) -> Result<Option<SegmentOperation>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM segment_operations WHERE clip_id = ?1 AND undone = 0 ORDER BY id DESC LIMIT 1",
+            SEGMENT_OPERATION_COLUMNS
+        ),
+        params![clip_id],
+        segment_operation_from_row,
+    )
+    .optional()
+}
+
+/// The operation `redo` would reapply next.
+pub fn get_first_undone_segment_operation(conn: &Connection, clip_id: &str) -> Result<Option<SegmentOperation>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM segment_operations WHERE clip_id = ?1 AND undone = 1 ORDER BY id ASC LIMIT 1",
+            SEGMENT_OPERATION_COLUMNS
+        ),
+        params![clip_id],
+        segment_operation_from_row,
+    )
+    .optional()
+}
+
+pub fn set_segment_operation_undone(conn: &Connection, id: i64, undone: bool) -> Result<()> {
+    conn.execute("UPDATE segment_operations SET undone = ?1 WHERE id = ?2", params![undone, id])?;
+    Ok(())
+}
+
+/// Folds a follow-up edit into an existing operation.
+pub fn update_segment_operation_after(conn: &Connection, id: i64, after_value: Option<&str>, created_at: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE segment_operations SET after_value = ?1, created_at = ?2 WHERE id = ?3",
+        params![after_value, created_at, id],
+    )?;
+    Ok(())
+}
+
+/// Drops the redo stack, which a new edit invalidates.
+pub fn delete_undone_segment_operations(conn: &Connection, clip_id: &str) -> Result<usize> {
+    conn.execute("DELETE FROM segment_operations WHERE clip_id = ?1 AND undone = 1", params![clip_id])
+}
+
+pub fn delete_segment_operations(conn: &Connection, clip_id: &str) -> Result<usize> {
+    conn.execute("DELETE FROM segment_operations WHERE clip_id = ?1", params![clip_id])
+}
+
+pub fn delete_segments_for_clip(conn: &Connection, clip_id: &str) -> Result<usize> {
+    conn.execute("DELETE FROM segments WHERE clip_id = ?1", params![clip_id])
+}
+
+/// Segments whose clip no longer exists, left behind because foreign keys
+/// were never enforced.
+pub fn get_orphaned_segment_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM segments WHERE clip_id NOT IN (SELECT id FROM clips)")?;
+    let id_iter = stmt.query_map([], |row| row.get(0))?;
+    id_iter.collect()
+}
+
+pub fn delete_orphaned_segments(conn: &Connection) -> Result<usize> {
+    conn.execute("DELETE FROM segments WHERE clip_id NOT IN (SELECT id FROM clips)", [])
+}
+
 pub fn delete_segment(conn: &Connection, id: &str) -> Result<()> {
     conn.execute("DELETE FROM segments WHERE id = ?1", params![id])?;
     Ok(())
 }
 
-pub fn get_clip_by_original_name(conn: &Connection, name: &str) -> Result<Option<Clip>> {
-    let mut stmt = conn.prepare("SELECT id, original_name, backup_path, status, created_at FROM clips WHERE original_name = ?1")?;
-    let mut clip_iter = stmt.query_map(params![name], |row| {
-        Ok(Clip {
-            id: row.get(0)?,
-            original_name: row.get(1)?,
-            backup_path: row.get(2)?,
-            status: row.get(3)?,
-            created_at: row.get(4)?,
-        })
-    })?;
+pub fn get_clip_by_relative_path(conn: &Connection, relative_path: &str) -> Result<Option<Clip>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM clips WHERE relative_path = ?1", CLIP_COLUMNS))?;
+    let mut clip_iter = stmt.query_map(params![relative_path], clip_from_row)?;
 
     if let Some(res) = clip_iter.next() {
         Ok(Some(res?))
@@ -166,3 +794,44 @@ pub fn get_clip_by_original_name(conn: &Connection, name: &str) -> Result<Option
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_transitions() {
+        use ClipStatus::*;
+        // Every state each one may move to, including itself and `missing`
+        let table: [(ClipStatus, &[ClipStatus]); 9] = [
+            (New, &[New, InProgress, Skipped, Rejected, Missing]),
+            (InProgress, &[InProgress, New, Queued, Exporting, Done, Skipped, Rejected, Missing]),
+            (Queued, &[Queued, Exporting, InProgress, Failed, Missing]),
+            (Exporting, &[Exporting, Done, Failed, Missing]),
+            (Done, &[Done, New, InProgress, Queued, Exporting, Missing]),
+            (Failed, &[Failed, New, InProgress, Queued, Exporting, Missing]),
+            (Skipped, &[Skipped, New, InProgress, Missing]),
+            (Rejected, &[Rejected, New, InProgress, Missing]),
+            (Missing, &[Missing, New, InProgress]),
+        ];
+        for (from, allowed) in table {
+            for to in ClipStatus::ALL {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&to),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn status_names_round_trip() {
+        for status in ClipStatus::ALL {
+            assert_eq!(ClipStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(ClipStatus::parse("archived"), None);
+    }
+}
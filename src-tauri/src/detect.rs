@@ -0,0 +1,277 @@
+use crate::ffmpeg::{LoudnessSample, MotionSample};
+use serde::{Deserialize, Serialize};
+
+/// Readings below this are treated as digital silence and ignored when
+/// computing the clip's baseline loudness.
+const SILENCE_FLOOR_LUFS: f64 = -70.0;
+
+/// A segment proposed by one of the detectors. It is not stored until the
+/// user accepts it through `add_segment`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Time of the strongest reading inside the segment.
+    pub peak_ms: i64,
+    /// Detector-specific intensity; higher is more interesting.
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightOptions {
+    /// How far above the clip's median loudness (in LU) a reading must be to count as a peak.
+    pub threshold_lu: f64,
+    /// Peaks closer together than this are merged into one highlight.
+    pub merge_gap_ms: i64,
+    pub pre_roll_ms: i64,
+    pub post_roll_ms: i64,
+    pub max_results: Option<usize>,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            threshold_lu: 8.0,
+            merge_gap_ms: 2000,
+            pre_roll_ms: 3000,
+            post_roll_ms: 2000,
+            max_results: None,
+        }
+    }
+}
+
+/// Finds loud moments in a loudness curve and proposes padded segments around
+/// them, ranked by how far their peak rises above the clip's baseline.
+pub fn find_audio_highlights(
+    samples: &[LoudnessSample],
+    duration_ms: i64,
+    options: &HighlightOptions,
+) -> Vec<SuggestedSegment> {
+    let mut audible: Vec<f64> = samples
+        .iter()
+        .map(|s| s.momentary_lufs)
+        .filter(|l| *l > SILENCE_FLOOR_LUFS)
+        .collect();
+    if audible.is_empty() {
+        return Vec::new();
+    }
+    audible.sort_by(|a, b| a.total_cmp(b));
+    let baseline = audible[audible.len() / 2];
+    let threshold = baseline + options.threshold_lu;
+
+    // Collect runs of consecutive readings above the threshold, bridging gaps
+    // shorter than merge_gap_ms.
+    let mut peaks: Vec<SuggestedSegment> = Vec::new();
+    for sample in samples.iter().filter(|s| s.momentary_lufs >= threshold) {
+        let score = sample.momentary_lufs - baseline;
+        match peaks.last_mut() {
+            Some(last) if sample.time_ms - last.end_ms <= options.merge_gap_ms => {
+                last.end_ms = sample.time_ms;
+                if score > last.score {
+                    last.score = score;
+                    last.peak_ms = sample.time_ms;
+                }
+            }
+            _ => peaks.push(SuggestedSegment {
+                start_ms: sample.time_ms,
+                end_ms: sample.time_ms,
+                peak_ms: sample.time_ms,
+                score,
+            }),
+        }
+    }
+
+    let padded = peaks.into_iter().map(|p| SuggestedSegment {
+        start_ms: (p.start_ms - options.pre_roll_ms).max(0),
+        end_ms: (p.end_ms + options.post_roll_ms).min(duration_ms.max(p.end_ms)),
+        ..p
+    });
+    let mut highlights = merge_overlapping(padded);
+
+    highlights.sort_by(|a, b| b.score.total_cmp(&a.score));
+    if let Some(max) = options.max_results {
+        highlights.truncate(max);
+    }
+    highlights
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionOptions {
+    /// 0.0..=1.0; higher values flag smaller frame differences as motion.
+    pub sensitivity: f64,
+    /// Motion periods separated by less than this are joined.
+    pub min_gap_ms: i64,
+    /// Periods shorter than this (after joining) are dropped as noise.
+    pub min_duration_ms: i64,
+    pub pre_roll_ms: i64,
+    pub post_roll_ms: i64,
+}
+
+impl Default for MotionOptions {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.5,
+            min_gap_ms: 3000,
+            min_duration_ms: 1000,
+            pre_roll_ms: 1000,
+            post_roll_ms: 1000,
+        }
+    }
+}
+
+impl MotionOptions {
+    /// Scene score a frame must reach to count as motion. Static-camera
+    /// footage sits well below 0.01; people walking through frame land around 0.02-0.1.
+    fn scene_threshold(&self) -> f64 {
+        (0.1 * (1.0 - self.sensitivity.clamp(0.0, 1.0))).max(0.002)
+    }
+}
+
+/// Turns per-frame difference scores into periods of activity, in
+/// chronological order.
+pub fn find_motion_periods(
+    samples: &[MotionSample],
+    duration_ms: i64,
+    options: &MotionOptions,
+) -> Vec<SuggestedSegment> {
+    let threshold = options.scene_threshold();
+
+    let mut periods: Vec<SuggestedSegment> = Vec::new();
+    for sample in samples.iter().filter(|s| s.scene_score >= threshold) {
+        match periods.last_mut() {
+            Some(last) if sample.time_ms - last.end_ms <= options.min_gap_ms => {
+                last.end_ms = sample.time_ms;
+                if sample.scene_score > last.score {
+                    last.score = sample.scene_score;
+                    last.peak_ms = sample.time_ms;
+                }
+            }
+            _ => periods.push(SuggestedSegment {
+                start_ms: sample.time_ms,
+                end_ms: sample.time_ms,
+                peak_ms: sample.time_ms,
+                score: sample.scene_score,
+            }),
+        }
+    }
+
+    let padded = periods
+        .into_iter()
+        .filter(|p| p.end_ms - p.start_ms >= options.min_duration_ms)
+        .map(|p| SuggestedSegment {
+            start_ms: (p.start_ms - options.pre_roll_ms).max(0),
+            end_ms: (p.end_ms + options.post_roll_ms).min(duration_ms.max(p.end_ms)),
+            ..p
+        });
+    merge_overlapping(padded)
+}
+
+/// Merges segments (given in start order) whose ranges overlap, keeping the
+/// stronger peak.
+fn merge_overlapping(segments: impl Iterator<Item = SuggestedSegment>) -> Vec<SuggestedSegment> {
+    let mut merged: Vec<SuggestedSegment> = Vec::new();
+    for seg in segments {
+        match merged.last_mut() {
+            Some(last) if seg.start_ms <= last.end_ms => {
+                last.end_ms = last.end_ms.max(seg.end_ms);
+                if seg.score > last.score {
+                    last.score = seg.score;
+                    last.peak_ms = seg.peak_ms;
+                }
+            }
+            _ => merged.push(seg),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One reading every 100 ms at a steady level.
+    fn curve(duration_ms: i64, lufs: f64) -> Vec<LoudnessSample> {
+        (0..duration_ms / 100)
+            .map(|i| LoudnessSample { time_ms: i * 100, momentary_lufs: lufs })
+            .collect()
+    }
+
+    fn set(samples: &mut [LoudnessSample], time_ms: i64, lufs: f64) {
+        samples[(time_ms / 100) as usize].momentary_lufs = lufs;
+    }
+
+    fn spans(highlights: &[SuggestedSegment]) -> Vec<(i64, i64, i64, f64)> {
+        highlights.iter().map(|h| (h.start_ms, h.end_ms, h.peak_ms, h.score)).collect()
+    }
+
+    #[test]
+    fn baseline_is_the_median_of_audible_readings() {
+        // Mostly digital silence; counting it would make every sound a peak
+        let mut samples = curve(100_000, -30.0);
+        for sample in &mut samples[..600] {
+            sample.momentary_lufs = -120.0;
+        }
+        set(&mut samples, 80_000, -20.0);
+
+        let highlights = find_audio_highlights(&samples, 100_000, &HighlightOptions::default());
+        assert_eq!(spans(&highlights), vec![(77_000, 82_000, 80_000, 10.0)]);
+    }
+
+    #[test]
+    fn ranks_highlights_by_rise_above_baseline() {
+        let mut samples = curve(60_000, -30.0);
+        set(&mut samples, 10_000, -18.0);
+        set(&mut samples, 10_100, -15.0);
+        // Within merge_gap_ms of the run before it
+        set(&mut samples, 11_000, -20.0);
+        set(&mut samples, 40_000, -10.0);
+        set(&mut samples, 50_000, -21.0);
+        // Loud, but less than threshold_lu above the baseline
+        set(&mut samples, 30_000, -23.0);
+
+        let highlights = find_audio_highlights(&samples, 60_000, &HighlightOptions::default());
+        assert_eq!(
+            spans(&highlights),
+            vec![
+                (37_000, 42_000, 40_000, 20.0),
+                (7_000, 13_000, 10_100, 15.0),
+                (47_000, 52_000, 50_000, 9.0),
+            ]
+        );
+
+        let options = HighlightOptions { max_results: Some(2), ..HighlightOptions::default() };
+        let top = find_audio_highlights(&samples, 60_000, &options);
+        assert_eq!(top.iter().map(|h| h.peak_ms).collect::<Vec<_>>(), vec![40_000, 10_100]);
+    }
+
+    #[test]
+    fn padding_is_clamped_to_the_clip_and_overlaps_are_merged() {
+        let mut samples = curve(60_000, -30.0);
+        set(&mut samples, 1_000, -15.0);
+        // Too far apart to merge as peaks, but their padding overlaps
+        set(&mut samples, 20_000, -18.0);
+        set(&mut samples, 24_000, -12.0);
+        set(&mut samples, 59_900, -16.0);
+
+        let mut highlights = find_audio_highlights(&samples, 60_000, &HighlightOptions::default());
+        highlights.sort_by_key(|h| h.start_ms);
+        assert_eq!(
+            spans(&highlights),
+            vec![
+                (0, 3_000, 1_000, 15.0),
+                (17_000, 26_000, 24_000, 18.0),
+                (56_900, 60_000, 59_900, 14.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn silence_has_no_highlights() {
+        let options = HighlightOptions::default();
+        assert!(find_audio_highlights(&[], 60_000, &options).is_empty());
+        assert!(find_audio_highlights(&curve(60_000, -90.0), 60_000, &options).is_empty());
+        assert!(find_audio_highlights(&curve(60_000, -30.0), 60_000, &options).is_empty());
+    }
+}
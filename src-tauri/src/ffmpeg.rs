@@ -2,6 +2,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use std::io::Result;
 use std::path::Path;
+use tauri_plugin_shell::process::CommandEvent;
 use tauri_plugin_shell::ShellExt;
 use tauri::AppHandle;
 
@@ -31,7 +32,7 @@ pub async fn get_video_info(app_handle: &AppHandle, file_path: &Path) -> Result<
     let sidecar_command = app_handle
         .shell()
         .sidecar("ffprobe")
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
 
     let output = sidecar_command
         .args([
@@ -47,16 +48,13 @@ pub async fn get_video_info(app_handle: &AppHandle, file_path: &Path) -> Result<
         ])
         .output()
         .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-                "ffprobe failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        ));
+        return Err(std::io::Error::other(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
     let parsed: FfprobeOutput = serde_json::from_slice(&output.stdout)?;
@@ -83,53 +81,445 @@ pub async fn get_video_info(app_handle: &AppHandle, file_path: &Path) -> Result<
     Ok(VideoInfo { duration_sec, fps })
 }
 
-pub async fn extract_frame(app_handle: &AppHandle, file_path: &Path, time_ms: i64) -> Result<Vec<u8>> {
-    let time_sec = time_ms as f64 / 1000.0;
-    
+#[derive(Debug, Deserialize)]
+struct ProbeFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    pix_fmt: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    r_frame_rate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    format: ProbeFormat,
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoStreamInfo {
+    pub codec: String,
+    pub pix_fmt: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+}
+
+/// Container and stream details for a media file.
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaProbe {
+    pub format_name: String,
+    pub duration_sec: f64,
+    pub video: Option<VideoStreamInfo>,
+    pub audio_codec: Option<String>,
+}
+
+impl MediaProbe {
+    /// Whether the webview is unlikely to play or scrub this source smoothly:
+    /// codecs it cannot decode, 10-bit or 4:2:2/4:4:4 pixel formats, or
+    /// resolutions and frame rates too heavy to seek through.
+    pub fn needs_preview_proxy(&self) -> bool {
+        let Some(video) = &self.video else {
+            return false;
+        };
+        let browser_codec = matches!(video.codec.as_str(), "h264" | "vp8" | "vp9" | "av1");
+        let browser_pix_fmt = matches!(video.pix_fmt.as_str(), "yuv420p" | "yuvj420p" | "nv12");
+        !browser_codec || !browser_pix_fmt || video.height > 1440 || video.fps > 65.0
+    }
+
+    /// Duration rounded up to whole milliseconds; `None` if ffprobe did not
+    /// report one.
+    pub fn duration_ms(&self) -> Option<i64> {
+        (self.duration_sec > 0.0).then(|| (self.duration_sec * 1000.0).ceil() as i64)
+    }
+}
+
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (den > 0.0).then(|| num / den)
+}
+
+pub async fn probe_media(app_handle: &AppHandle, file_path: &Path) -> Result<MediaProbe> {
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("ffprobe")
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let output = sidecar_command
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=format_name,duration:stream=codec_type,codec_name,pix_fmt,width,height,r_frame_rate",
+            "-of",
+            "json",
+            file_path.to_str().unwrap(),
+        ])
+        .output()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    let parsed: ProbeOutput = serde_json::from_slice(&output.stdout)?;
+
+    let video = parsed
+        .streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("video"))
+        .map(|s| VideoStreamInfo {
+            codec: s.codec_name.clone().unwrap_or_default(),
+            pix_fmt: s.pix_fmt.clone().unwrap_or_default(),
+            width: s.width.unwrap_or(0),
+            height: s.height.unwrap_or(0),
+            fps: s.r_frame_rate.as_deref().and_then(parse_frame_rate).unwrap_or(30.0),
+        });
+    let audio_codec = parsed
+        .streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("audio"))
+        .and_then(|s| s.codec_name.clone());
+
+    Ok(MediaProbe {
+        format_name: parsed.format.format_name.unwrap_or_default(),
+        duration_sec: parsed
+            .format
+            .duration
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(0.0),
+        video,
+        audio_codec,
+    })
+}
+
+/// Transcodes a lightweight 720p H.264 preview with a short GOP so the
+/// webview can seek it frame-accurately. Writes to a temporary file first so
+/// a half-finished proxy is never served.
+pub async fn generate_preview_proxy(
+    app_handle: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    source: &MediaProbe,
+) -> Result<()> {
+    let tmp_path = output_path.with_extension("partial.mp4");
+
+    let mut filters = vec!["format=yuv420p".to_string()];
+    if let Some(video) = &source.video {
+        if video.height > 720 {
+            filters.push("scale=-2:720".to_string());
+        }
+        if video.fps > 60.0 {
+            filters.push("fps=60".to_string());
+        }
+    }
+    let filter_chain = filters.join(",");
+
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let output = sidecar_command
+        .args([
+            "-y",
+            "-i",
+            input_path.to_str().unwrap(),
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a:0?",
+            "-vf",
+            &filter_chain,
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-crf",
+            "26",
+            "-g",
+            "10",
+            "-sc_threshold",
+            "0",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "128k",
+            "-ac",
+            "2",
+            "-movflags",
+            "+faststart",
+            tmp_path.to_str().unwrap(),
+        ])
+        .output()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(std::io::Error::other(format!(
+            "ffmpeg proxy generation failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    std::fs::rename(&tmp_path, output_path)
+}
+
+/// Transcodes one HLS chunk as MPEG-TS. Timestamps are offset to the chunk's
+/// position in the source so consecutive chunks play back seamlessly.
+pub async fn export_hls_segment(
+    app_handle: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    start_ms: i64,
+    length_ms: i64,
+) -> Result<()> {
+    let start_sec = start_ms as f64 / 1000.0;
+    let length_sec = length_ms as f64 / 1000.0;
+    let tmp_path = output_path.with_extension("partial.ts");
+
     let sidecar_command = app_handle
         .shell()
         .sidecar("ffmpeg")
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
-        
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
     let output = sidecar_command
         .args([
+            "-y",
             "-ss",
-            &time_sec.to_string(),
+            &start_sec.to_string(),
             "-i",
-            file_path.to_str().unwrap(),
-            "-frames:v",
-            "1",
-            "-q:v",
+            input_path.to_str().unwrap(),
+            "-t",
+            &length_sec.to_string(),
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a:0?",
+            "-vf",
+            "scale=-2:'min(1080,ih)',format=yuv420p",
+            "-c:v",
+            "libx264",
+            "-preset",
+            "veryfast",
+            "-crf",
+            "23",
+            "-force_key_frames",
+            "expr:eq(n,0)",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "160k",
+            "-ac",
             "2",
+            "-output_ts_offset",
+            &start_sec.to_string(),
             "-f",
-            "image2pipe",
-            "-vcodec",
-            "mjpeg",
-            "-",
+            "mpegts",
+            tmp_path.to_str().unwrap(),
         ])
         .output()
         .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
 
     if !output.status.success() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-                "ffmpeg frame extraction failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        ));
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(std::io::Error::other(format!(
+            "ffmpeg HLS segment failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    std::fs::rename(&tmp_path, output_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    Jpg,
+    Png,
+}
+
+impl FrameFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpg => "jpg",
+            Self::Png => "png",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+
+    fn codec(self) -> &'static str {
+        match self {
+            Self::Jpg => "mjpeg",
+            Self::Png => "png",
+        }
+    }
+}
+
+/// Decodes a single frame at `time_ms`, optionally scaled to `width` pixels
+/// wide (keeping aspect ratio), and returns the encoded image bytes.
+pub async fn extract_frame(
+    app_handle: &AppHandle,
+    file_path: &Path,
+    time_ms: i64,
+    width: Option<u32>,
+    format: FrameFormat,
+) -> Result<Vec<u8>> {
+    let time_sec = time_ms as f64 / 1000.0;
+    let scale = width.map(|w| format!("scale={}:-2", w));
+
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let mut args = vec![
+        "-ss".to_string(),
+        time_sec.to_string(),
+        "-i".to_string(),
+        file_path.to_str().unwrap().to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ];
+    if let Some(scale) = scale {
+        args.extend(["-vf".to_string(), scale]);
+    }
+    if format == FrameFormat::Jpg {
+        args.extend(["-q:v".to_string(), "2".to_string()]);
+    }
+    args.extend([
+        "-f".to_string(),
+        "image2pipe".to_string(),
+        "-vcodec".to_string(),
+        format.codec().to_string(),
+        "-".to_string(),
+    ]);
+
+    let output = sidecar_command
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "ffmpeg frame extraction failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
     Ok(output.stdout)
 }
 
+/// Container written by `export_segment`. The source's family is kept when it
+/// can hold the clip's codecs; otherwise the first container that can is
+/// used, so e.g. H.264 in Matroska is remuxed to MP4 and VP9 to WebM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputContainer {
+    Mp4,
+    Mov,
+    Webm,
+}
+
+impl OutputContainer {
+    pub fn for_source(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "mov" => Self::Mov,
+            "webm" => Self::Webm,
+            _ => Self::Mp4,
+        }
+    }
+
+    /// Picks the container from the codecs recorded at ingest. Clips probed
+    /// before codecs were recorded fall back to the file extension.
+    pub fn for_clip(path: &Path, video_codec: Option<&str>, audio_codec: Option<&str>) -> Self {
+        let preferred = Self::for_source(path);
+        let Some(video_codec) = video_codec else {
+            return preferred;
+        };
+        [preferred, Self::Mp4, Self::Mov, Self::Webm]
+            .into_iter()
+            .find(|c| c.accepts(video_codec, audio_codec))
+            .unwrap_or(preferred)
+    }
+
+    /// Whether the source streams can be copied into this container as-is.
+    pub fn accepts(self, video_codec: &str, audio_codec: Option<&str>) -> bool {
+        let (video, audio): (&[&str], &[&str]) = match self {
+            Self::Mp4 => (&["h264", "hevc", "av1", "mpeg4"], &["aac", "mp3", "ac3", "eac3", "alac", "opus"]),
+            Self::Mov => (
+                &["h264", "hevc", "prores", "mpeg4", "mjpeg"],
+                &["aac", "mp3", "alac", "pcm_s16le", "pcm_s24le", "pcm_f32le"],
+            ),
+            Self::Webm => (&["vp8", "vp9", "av1"], &["opus", "vorbis"]),
+        };
+        video.contains(&video_codec) && audio_codec.is_none_or(|a| audio.contains(&a))
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+            Self::Webm => "webm",
+        }
+    }
+
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 | Self::Mov => &[
+                "-c:v", "libx264", "-preset", "fast", "-crf", "18", "-c:a", "aac", "-b:a", "192k",
+            ],
+            Self::Webm => &[
+                "-c:v", "libvpx-vp9", "-crf", "30", "-b:v", "0", "-row-mt", "1", "-c:a", "libopus", "-b:a", "160k",
+            ],
+        }
+    }
+}
+
+/// Writes `start_ms..end_ms` of the input to `output_path`. Re-encoding cuts
+/// on the exact frame; `stream_copy` keeps the source streams, which is much
+/// faster but starts on the keyframe at or before `start_ms`.
 pub async fn export_segment(
     app_handle: &AppHandle,
     input_path: &Path,
     output_path: &Path,
     start_ms: i64,
     end_ms: i64,
+    container: OutputContainer,
+    stream_copy: bool,
 ) -> Result<()> {
     let start_sec = start_ms as f64 / 1000.0;
     let end_sec = end_ms as f64 / 1000.0;
@@ -145,43 +535,198 @@ pub async fn export_segment(
     let sidecar_command = app_handle
         .shell()
         .sidecar("ffmpeg")
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
 
-    let output = sidecar_command
-        .args([
+    let command = if stream_copy {
+        // Seeking the input lands on a keyframe, which copied video must start on
+        sidecar_command.args([
             "-y",
             "-ss",
-            &preseek.to_string(),
+            &start_sec.to_string(),
             "-i",
             input_path.to_str().unwrap(),
-            "-ss",
-            &exact_ss.to_string(),
             "-t",
             &duration.to_string(),
-            "-c:v",
-            "libx264",
-            "-preset",
-            "fast",
-            "-crf",
-            "18",
-            "-c:a",
-            "aac",
-            "-b:a",
-            "192k",
-            output_path.to_str().unwrap(),
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a:0?",
+            "-c",
+            "copy",
+            "-avoid_negative_ts",
+            "make_zero",
         ])
+    } else {
+        sidecar_command
+            .args([
+                "-y",
+                "-ss",
+                &preseek.to_string(),
+                "-i",
+                input_path.to_str().unwrap(),
+                "-ss",
+                &exact_ss.to_string(),
+                "-t",
+                &duration.to_string(),
+            ])
+            .args(container.codec_args())
+    };
+    let output = command
+        .arg(output_path.to_str().unwrap())
         .output()
         .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
 
     if !output.status.success() {
         let err_msg = String::from_utf8_lossy(&output.stderr).to_string();
         eprintln!("FFmpeg Export Failed! Stderr: {}", err_msg);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("FFmpeg export failed: {}", err_msg),
-        ));
+        return Err(std::io::Error::other(format!("FFmpeg export failed: {}", err_msg)));
     }
 
     Ok(())
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessSample {
+    pub time_ms: i64,
+    pub momentary_lufs: f64,
+}
+
+/// Runs the `ebur128` filter over the clip's audio and returns one momentary
+/// loudness reading (400ms window) every 100ms. Fails with `InvalidInput` if
+/// `source` has no audio stream.
+pub async fn measure_loudness(
+    app_handle: &AppHandle,
+    file_path: &Path,
+    source: &MediaProbe,
+) -> Result<Vec<LoudnessSample>> {
+    if source.audio_codec.is_none() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Clip has no audio"));
+    }
+
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let output = sidecar_command
+        .args([
+            "-nostats",
+            "-i",
+            file_path.to_str().unwrap(),
+            "-vn",
+            "-af",
+            "ebur128=framelog=info",
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "ffmpeg loudness analysis failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    // ebur128 logs to stderr, one line per 100ms:
+    // [Parsed_ebur128_0 @ ...] t: 1.2  TARGET:-23 LUFS  M: -18.4 S: -21.0  I: -22.3 LUFS ...
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let samples = stderr
+        .lines()
+        .filter(|line| line.contains("Parsed_ebur128"))
+        .filter_map(|line| {
+            let t = parse_log_value(line, "t:")?;
+            let m = parse_log_value(line, "M:")?;
+            Some(LoudnessSample {
+                time_ms: (t * 1000.0).round() as i64,
+                momentary_lufs: m,
+            })
+        })
+        .collect();
+
+    Ok(samples)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MotionSample {
+    pub time_ms: i64,
+    /// Normalised frame difference to the previous frame, 0.0..=1.0.
+    pub scene_score: f64,
+}
+
+/// Decodes a downscaled 10fps copy of the video and returns the frame-to-frame
+/// difference score for every frame. `on_progress` receives the decode position
+/// in milliseconds as ffmpeg works through the file.
+pub async fn measure_motion(
+    app_handle: &AppHandle,
+    file_path: &Path,
+    mut on_progress: impl FnMut(i64),
+) -> Result<Vec<MotionSample>> {
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let (mut rx, _child) = sidecar_command
+        .args([
+            "-nostats",
+            "-i",
+            file_path.to_str().unwrap(),
+            "-an",
+            "-vf",
+            "scale=160:-2,fps=10,select='gte(scene,0)',metadata=print:key=lavfi.scene_score",
+            "-f",
+            "null",
+            "-",
+        ])
+        .spawn()
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // metadata=print logs two lines per frame:
+    // [Parsed_metadata_3 @ ...] frame:12   pts:1200  pts_time:1.2
+    // [Parsed_metadata_3 @ ...] lavfi.scene_score=0.004211
+    let mut samples = Vec::new();
+    let mut frame_time_ms = None;
+    let mut last_stderr = String::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stderr(bytes) => {
+                let line = String::from_utf8_lossy(&bytes);
+                if let Some(t) = parse_log_value(&line, "pts_time:") {
+                    let time_ms = (t * 1000.0).round() as i64;
+                    frame_time_ms = Some(time_ms);
+                    on_progress(time_ms);
+                } else if let Some(score) = parse_log_value(&line, "lavfi.scene_score=") {
+                    if let Some(time_ms) = frame_time_ms.take() {
+                        samples.push(MotionSample { time_ms, scene_score: score });
+                    }
+                } else if !line.trim().is_empty() {
+                    last_stderr = line.trim().to_string();
+                }
+            }
+            CommandEvent::Error(e) => return Err(std::io::Error::other(e)),
+            CommandEvent::Terminated(payload) if payload.code != Some(0) => {
+                return Err(std::io::Error::other(format!(
+                    "ffmpeg motion analysis failed: {}",
+                    last_stderr
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Parses the number following `key` in an ffmpeg filter log line, e.g. `M: -18.4`.
+fn parse_log_value(line: &str, key: &str) -> Option<f64> {
+    let rest = line[line.find(key)? + key.len()..].trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
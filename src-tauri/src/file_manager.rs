@@ -1,6 +1,108 @@
+use crate::fingerprint;
+use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{ErrorKind, Read, Result, Write};
 use std::path::{Path, PathBuf};
-use std::io::Result;
+
+/// Chunk size for copies; progress is reported once per chunk.
+const COPY_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// What happens to an original in IN when its clip is first opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupMode {
+    /// Move it into BACKUP (copying across filesystems if needed).
+    #[default]
+    Move,
+    /// Copy it into BACKUP and leave IN untouched.
+    Copy,
+    /// Leave it where it is and use it in place, e.g. on read-only shares.
+    Reference,
+}
+
+/// Per-folder settings stored in `.cliptrim/config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderConfig {
+    /// File extensions (lowercase, without the dot) picked up from IN.
+    pub media_extensions: Vec<String>,
+    pub backup_mode: BackupMode,
+    pub retention: RetentionPolicy,
+    pub layout: LayoutConfig,
+}
+
+/// Where the working folders live. Relative paths are resolved against the
+/// working folder root; absolute ones may point elsewhere, e.g. OUT on a
+/// shared drive. `.cliptrim/` always stays in the root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub in_dir: String,
+    pub out_dir: String,
+    pub backup_dir: String,
+    pub archive_dir: String,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            in_dir: "IN".to_string(),
+            out_dir: "OUT".to_string(),
+            backup_dir: "BACKUP".to_string(),
+            archive_dir: "ARCHIVE".to_string(),
+        }
+    }
+}
+
+/// What to do with backups of finished clips once they are no longer needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    #[default]
+    Delete,
+    /// Move them to the archive folder (`ARCHIVE/` by default).
+    Archive,
+}
+
+/// When backups of `done` clips may be cleaned up. Both limits are off by
+/// default; nothing is removed without an explicit cleanup call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Backups of clips ingested more than this many days ago.
+    pub max_age_days: Option<u32>,
+    /// Oldest backups first until BACKUP fits in this many gigabytes.
+    pub max_backup_gb: Option<f64>,
+    pub action: RetentionAction,
+}
+
+impl Default for FolderConfig {
+    fn default() -> Self {
+        Self {
+            media_extensions: ["mp4", "mov", "mkv", "webm", "m4v"]
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+            backup_mode: BackupMode::default(),
+            retention: RetentionPolicy::default(),
+            layout: LayoutConfig::default(),
+        }
+    }
+}
+
+impl FolderConfig {
+    fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(std::io::Error::other),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn accepts_extension(&self, ext: &str) -> bool {
+        self.media_extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+}
 
 #[derive(Clone)]
 pub struct WorkingDirectory {
@@ -8,64 +110,377 @@ pub struct WorkingDirectory {
     pub in_dir: PathBuf,
     pub out_dir: PathBuf,
     pub backup_dir: PathBuf,
+    pub archive_dir: PathBuf,
+    pub proxies_dir: PathBuf,
+    pub hls_dir: PathBuf,
     pub db_path: PathBuf,
+    pub config_path: PathBuf,
+    pub config: FolderConfig,
 }
 
 impl WorkingDirectory {
     pub fn new(root: &Path) -> Result<Self> {
-        let in_dir = root.join("IN");
-        let out_dir = root.join("OUT");
-        let backup_dir = root.join("BACKUP");
+        let wd = Self::open_read_only(root)?;
+        wd.prepare()?;
+        Ok(wd)
+    }
+
+    /// Resolves the folder's paths without creating or writing anything, for
+    /// instances that only browse it, e.g. on a read-only share or while
+    /// another instance holds the lock.
+    pub fn open_read_only(root: &Path) -> Result<Self> {
+        let config = FolderConfig::load(&root.join(".cliptrim").join("config.json"))?;
+        Ok(Self::with_config(root, config))
+    }
+
+    /// Lays out a working folder for `config`. Nothing is touched on disk.
+    fn with_config(root: &Path, config: FolderConfig) -> Self {
+        let layout = &config.layout;
+        let in_dir = resolve_dir(root, &layout.in_dir);
+        let out_dir = resolve_dir(root, &layout.out_dir);
+        let backup_dir = resolve_dir(root, &layout.backup_dir);
+        let archive_dir = resolve_dir(root, &layout.archive_dir);
         let cliptrim_dir = root.join(".cliptrim");
+        let proxies_dir = cliptrim_dir.join("proxies");
+        let hls_dir = cliptrim_dir.join("hls");
         let db_path = cliptrim_dir.join("cliptrim.db");
+        let config_path = cliptrim_dir.join("config.json");
 
-        fs::create_dir_all(&in_dir)?;
-        fs::create_dir_all(&out_dir)?;
-        fs::create_dir_all(&backup_dir)?;
-        fs::create_dir_all(&cliptrim_dir)?;
-
-        Ok(Self {
+        Self {
             root: root.to_path_buf(),
             in_dir,
             out_dir,
             backup_dir,
+            archive_dir,
+            proxies_dir,
+            hls_dir,
             db_path,
-        })
+            config_path,
+            config,
+        }
+    }
+
+    /// Creates the folders and checks that the ones ClipTrim writes to are
+    /// writable.
+    fn prepare(&self) -> Result<()> {
+        let cliptrim_dir = self.db_path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(&self.in_dir)?;
+        fs::create_dir_all(&self.out_dir)?;
+        fs::create_dir_all(&self.backup_dir)?;
+        fs::create_dir_all(cliptrim_dir)?;
+        fs::create_dir_all(&self.proxies_dir)?;
+        fs::create_dir_all(&self.hls_dir)?;
+
+        ensure_writable("OUT", &self.out_dir)?;
+        ensure_writable(".cliptrim", cliptrim_dir)?;
+        if self.config.backup_mode != BackupMode::Reference {
+            ensure_writable("BACKUP", &self.backup_dir)?;
+        }
+        // Only moving originals out of IN needs write access there.
+        if self.config.backup_mode == BackupMode::Move {
+            ensure_writable("IN", &self.in_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Validates and applies new settings, then writes them to `config.json`.
+    /// Nothing is saved if the new layout is unusable.
+    pub fn save_config(&mut self, config: FolderConfig) -> Result<()> {
+        let updated = Self::with_config(&self.root, config);
+        updated.prepare()?;
+        let contents = serde_json::to_string_pretty(&updated.config).map_err(std::io::Error::other)?;
+        fs::write(&self.config_path, contents)?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Whether `config` would move IN or BACKUP, which the clips' stored
+    /// paths point into.
+    pub fn relocates_clips(&self, config: &FolderConfig) -> bool {
+        resolve_dir(&self.root, &config.layout.in_dir) != self.in_dir
+            || resolve_dir(&self.root, &config.layout.backup_dir) != self.backup_dir
     }
 
+    /// Folders media may be served from.
+    pub fn media_roots(&self) -> Vec<&Path> {
+        vec![&self.root, &self.in_dir, &self.backup_dir, &self.archive_dir]
+    }
+
+    /// Lists media files anywhere under IN. Hidden folders (e.g. `.sync`) are skipped.
     pub fn scan_in_folder(&self) -> Result<Vec<PathBuf>> {
         let mut entries = Vec::new();
         if self.in_dir.exists() {
-            for entry in fs::read_dir(&self.in_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path.extension() {
-                        if ext.to_string_lossy().to_lowercase() == "mp4" {
-                            entries.push(path);
-                        }
+            self.scan_dir(&self.in_dir, &mut entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn scan_dir(&self, dir: &Path, entries: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_dir() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    self.scan_dir(&path, entries)?;
+                }
+            } else if path.is_file() {
+                if let Some(ext) = path.extension() {
+                    if self.config.accepts_extension(&ext.to_string_lossy()) {
+                        entries.push(path);
                     }
                 }
             }
         }
-        Ok(entries)
+        Ok(())
+    }
+
+    /// Path of a file relative to IN with `/` separators, e.g. `day1/clip.mp4`.
+    /// This is what identifies a clip, so it must not depend on the OS.
+    pub fn relative_in_path(&self, file_path: &Path) -> Option<String> {
+        let relative = file_path.strip_prefix(&self.in_dir).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        Some(parts.join("/"))
     }
 
-    pub fn move_to_backup(&self, file_path: &Path) -> Result<PathBuf> {
+    /// Output folder for a clip, mirroring its subfolder inside IN.
+    pub fn out_dir_for(&self, relative_path: &str) -> Result<PathBuf> {
+        let dir = self.out_dir.join(relative_parent(relative_path));
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Free path in BACKUP for an IN file, keeping its subfolder within IN.
+    fn backup_target(&self, file_path: &Path) -> Result<PathBuf> {
         let file_name = file_path.file_name().unwrap().to_string_lossy();
         let file_stem = file_path.file_stem().unwrap().to_string_lossy();
         let ext = file_path.extension().unwrap().to_string_lossy();
 
-        let mut target_path = self.backup_dir.join(file_name.as_ref());
+        let backup_dir = match self.relative_in_path(file_path) {
+            Some(relative) => self.backup_dir.join(relative_parent(&relative)),
+            None => self.backup_dir.clone(),
+        };
+        fs::create_dir_all(&backup_dir)?;
+
+        let mut target_path = backup_dir.join(file_name.as_ref());
         let mut counter = 2;
 
         while target_path.exists() {
             let new_name = format!("{}_orig_v{}.{}", file_stem, counter, ext);
-            target_path = self.backup_dir.join(new_name);
+            target_path = backup_dir.join(new_name);
+            counter += 1;
+        }
+        Ok(target_path)
+    }
+
+    /// Secures an IN file according to the folder's `backup_mode` and returns
+    /// the path the clip should be read from from now on.
+    /// `on_progress(copied, total)` is called while bytes are being copied.
+    pub fn backup_original(&self, file_path: &Path, mut on_progress: impl FnMut(u64, u64)) -> Result<PathBuf> {
+        match self.config.backup_mode {
+            BackupMode::Reference => Ok(file_path.to_path_buf()),
+            BackupMode::Copy => {
+                let target_path = self.backup_target(file_path)?;
+                copy_verified(file_path, &target_path, &mut on_progress)?;
+                Ok(target_path)
+            }
+            BackupMode::Move => {
+                let target_path = self.backup_target(file_path)?;
+                move_file(file_path, &target_path, &mut on_progress)?;
+                Ok(target_path)
+            }
+        }
+    }
+
+    /// Moves a backed-up original back into IN at its recorded relative path.
+    /// If that name is taken, the file is restored as `<name>_restored_v<n>`.
+    /// In copy mode, when the file in IN still has the clip's recorded
+    /// content hash (or fingerprint, if it was never fully hashed), the
+    /// backup is deleted instead and the original in IN is returned.
+    pub fn restore_to_in(
+        &self,
+        file_path: &Path,
+        relative_path: &str,
+        fingerprint: Option<&str>,
+        content_hash: Option<&str>,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> Result<PathBuf> {
+        let in_dir = self.in_dir.join(relative_parent(relative_path));
+        let file_name = relative_path.rsplit('/').next().unwrap_or_default();
+
+        let original = in_dir.join(file_name);
+        if self.config.backup_mode == BackupMode::Copy
+            && original.is_file()
+            && is_same_content(&original, fingerprint, content_hash)
+        {
+            let size = fs::metadata(file_path)?.len();
+            fs::remove_file(file_path)?;
+            on_progress(size, size);
+            return Ok(original);
+        }
+
+        fs::create_dir_all(&in_dir)?;
+        let name_path = Path::new(file_name);
+        let file_stem = name_path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = name_path.extension().unwrap_or_default().to_string_lossy();
+
+        let mut target_path = in_dir.join(file_name);
+        let mut counter = 2;
+        while target_path.exists() {
+            target_path = in_dir.join(format!("{}_restored_v{}.{}", file_stem, counter, ext));
             counter += 1;
         }
 
-        fs::rename(file_path, &target_path)?;
+        move_file(file_path, &target_path, &mut on_progress)?;
+        Ok(target_path)
+    }
+
+    /// Moves a backup into the archive folder, keeping its place relative to BACKUP.
+    pub fn archive_backup(&self, file_path: &Path) -> Result<PathBuf> {
+        let relative = file_path.strip_prefix(&self.backup_dir).map_err(std::io::Error::other)?;
+        let target_path = self.archive_dir.join(relative);
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if target_path.exists() {
+            return Err(std::io::Error::new(ErrorKind::AlreadyExists, format!("{:?} already exists", target_path)));
+        }
+        move_file(file_path, &target_path, &mut |_, _| {})?;
         Ok(target_path)
     }
+
+    /// Whether a path lies inside BACKUP, i.e. is a copy ClipTrim owns.
+    pub fn is_in_backup(&self, path: &Path) -> bool {
+        path.starts_with(&self.backup_dir)
+    }
+}
+
+/// Whether `path` has the recorded content. Without a record nothing matches.
+fn is_same_content(path: &Path, fingerprint: Option<&str>, content_hash: Option<&str>) -> bool {
+    if let Some(content_hash) = content_hash {
+        return fingerprint::full_hash(path).is_ok_and(|hash| hash == content_hash);
+    }
+    fingerprint.is_some_and(|expected| {
+        fingerprint::quick_fingerprint(path).is_ok_and(|(_, fingerprint)| fingerprint == expected)
+    })
+}
+
+fn resolve_dir(root: &Path, configured: &str) -> PathBuf {
+    let path = Path::new(configured);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        root.join(path)
+    }
+}
+
+/// Creates and removes a probe file so a read-only share or missing
+/// permission is reported when the folder is selected, not mid-export.
+fn ensure_writable(label: &str, dir: &Path) -> Result<()> {
+    let probe = dir.join(".cliptrim_write_test");
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{} folder {} is not writable: {}", label, dir.display(), e)))
+}
+
+/// Lists every file under `dir`, skipping hidden folders.
+pub fn walk_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if dir.exists() {
+        walk_dir(dir, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                walk_dir(&path, files)?;
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Renames `from` to `to`, falling back to copy-verify-delete when they are
+/// on different filesystems.
+fn move_file(from: &Path, to: &Path, on_progress: &mut impl FnMut(u64, u64)) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(e),
+    }
+    copy_verified(from, to, on_progress)?;
+    if let Err(e) = fs::remove_file(from) {
+        // The verified copy is what matters; a read-only source just stays behind.
+        eprintln!("[backup] copied but could not remove {:?}: {}", from, e);
+    }
+    Ok(())
+}
+
+/// Copies `from` to `to` via a `.partial` file, then re-reads the copy and
+/// compares its BLAKE3 hash with the source before renaming it into place.
+fn copy_verified(from: &Path, to: &Path, on_progress: &mut impl FnMut(u64, u64)) -> Result<()> {
+    let partial = to.with_extension(format!(
+        "{}.partial",
+        to.extension().map(|e| e.to_string_lossy()).unwrap_or_default()
+    ));
+    let result = (|| {
+        let total = fs::metadata(from)?.len();
+        let mut reader = fs::File::open(from)?;
+        let mut writer = fs::File::create(&partial)?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0u8; COPY_CHUNK_BYTES];
+        let mut copied = 0u64;
+        on_progress(0, total);
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            writer.write_all(&buf[..n])?;
+            copied += n as u64;
+            on_progress(copied, total);
+        }
+        writer.sync_all()?;
+        drop(writer);
+
+        let copy_hash = hash_file(&partial)?;
+        if copied != total || copy_hash != hasher.finalize() {
+            return Err(std::io::Error::other(format!(
+                "verification failed copying {:?} to {:?}",
+                from, to
+            )));
+        }
+        fs::rename(&partial, to)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn hash_file(path: &Path) -> Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+/// Subfolder part of a relative clip path. Only plain folder names are kept,
+/// so a tampered DB value cannot point outside the working folder.
+fn relative_parent(relative_path: &str) -> PathBuf {
+    let mut parts: Vec<&str> = relative_path
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != "." && *p != "..")
+        .collect();
+    parts.pop();
+    parts.iter().collect()
 }
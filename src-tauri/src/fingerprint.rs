@@ -0,0 +1,130 @@
+use crate::commands::AppState;
+use crate::db::{self, Clip};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+/// Bytes hashed from each of the start, middle and end of a file.
+const SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// Set while a background hashing pass is running.
+static HASH_PASS_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Clips that share a quick fingerprint. `confirmed` is set once every clip
+/// in the group has the same full content hash.
+#[derive(Debug, serde::Serialize)]
+pub struct DuplicateGroup {
+    pub fingerprint: String,
+    pub confirmed: bool,
+    pub clips: Vec<Clip>,
+}
+
+/// Size and a BLAKE3 over the head, middle and tail of a file, as
+/// `<size>:<hex>`. Cheap enough to run on every ingest, even for
+/// multi-gigabyte recordings.
+pub fn quick_fingerprint(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = blake3::Hasher::new();
+
+    let mut offsets = vec![0];
+    if size > SAMPLE_BYTES {
+        offsets.push(size / 2 - SAMPLE_BYTES / 2);
+        offsets.push(size - SAMPLE_BYTES);
+    }
+    let mut buf = Vec::with_capacity(SAMPLE_BYTES as usize);
+    for offset in offsets {
+        file.seek(SeekFrom::Start(offset))?;
+        buf.clear();
+        (&mut file).take(SAMPLE_BYTES).read_to_end(&mut buf)?;
+        hasher.update(&buf);
+    }
+    Ok((size, format!("{}:{}", size, hasher.finalize().to_hex())))
+}
+
+/// BLAKE3 of the whole file.
+pub fn full_hash(path: &Path) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Groups clips sharing a fingerprint; clips without one are ignored.
+pub fn duplicate_groups(clips: Vec<Clip>) -> Vec<DuplicateGroup> {
+    let mut by_fingerprint: BTreeMap<String, Vec<Clip>> = BTreeMap::new();
+    for clip in clips {
+        if let Some(fingerprint) = clip.fingerprint.clone() {
+            by_fingerprint.entry(fingerprint).or_default().push(clip);
+        }
+    }
+    by_fingerprint
+        .into_iter()
+        .filter(|(_, clips)| clips.len() > 1)
+        .map(|(fingerprint, clips)| {
+            let confirmed = clips[0].content_hash.is_some()
+                && clips.iter().all(|c| c.content_hash == clips[0].content_hash);
+            DuplicateGroup { fingerprint, confirmed, clips }
+        })
+        .collect()
+}
+
+/// Starts a background pass that fills in missing fingerprints and full
+/// hashes, one clip at a time. Does nothing if a pass is already running.
+pub fn spawn_hash_pass(app_handle: AppHandle) {
+    if HASH_PASS_RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let mut attempted = HashSet::new();
+        loop {
+            let next = next_unhashed(&app_handle, &attempted);
+            let Some((clip_id, path, recorded_size)) = next else {
+                break;
+            };
+            attempted.insert(clip_id.clone());
+
+            let hashed = tauri::async_runtime::spawn_blocking(move || {
+                let (size, fingerprint) = quick_fingerprint(&path)?;
+                // Hashes of a file that changed since ingest would hide the change
+                if recorded_size.is_some_and(|recorded| recorded != size as i64) {
+                    return Ok(Err(size));
+                }
+                Ok::<_, std::io::Error>(Ok((size, fingerprint, full_hash(&path)?)))
+            })
+            .await;
+            match hashed {
+                Ok(Ok(Err(size))) => eprintln!(
+                    "[hash] clip_id={} is {} bytes, {} at ingest; not hashed, the integrity check reports it",
+                    clip_id,
+                    size,
+                    recorded_size.unwrap_or_default()
+                ),
+                Ok(Ok(Ok((size, fingerprint, content_hash)))) => {
+                    let state = app_handle.state::<AppState>();
+                    let db_guard = state.db.lock().unwrap();
+                    if let Some(conn) = db_guard.as_ref() {
+                        let _ = db::update_clip_hashes(conn, &clip_id, size as i64, &fingerprint, Some(&content_hash));
+                    }
+                }
+                Ok(Err(e)) => eprintln!("[hash] clip_id={} failed: {}", clip_id, e),
+                Err(e) => eprintln!("[hash] clip_id={} failed: {}", clip_id, e),
+            }
+        }
+        HASH_PASS_RUNNING.store(false, Ordering::SeqCst);
+    });
+}
+
+fn next_unhashed(app_handle: &AppHandle, attempted: &HashSet<String>) -> Option<(String, PathBuf, Option<i64>)> {
+    let state = app_handle.state::<AppState>();
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref()?;
+    db::get_clips_missing_hash(conn)
+        .ok()?
+        .into_iter()
+        .filter(|c| !attempted.contains(&c.id))
+        .map(|c| (c.id, PathBuf::from(c.backup_path), c.size_bytes))
+        .find(|(_, path, _)| path.exists())
+}
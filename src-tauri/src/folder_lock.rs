@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const LOCK_FILE: &str = "lock";
+
+/// How often the holder refreshes `heartbeat_at`.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(20);
+
+/// A lock whose heartbeat is older than this is assumed to belong to an
+/// instance that crashed or lost its connection to the share.
+const STALE_AFTER_SECS: i64 = 90;
+
+/// How long a takeover waits before checking that its write is the one that
+/// stuck, so two instances replacing the same stale lock cannot both win.
+const TAKEOVER_SETTLE: Duration = Duration::from_secs(2);
+
+/// Contents of `.cliptrim/lock`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub host: String,
+    pub pid: u32,
+    pub acquired_at: i64,
+    pub heartbeat_at: i64,
+    /// Distinguishes two acquisitions by the same process.
+    token: String,
+}
+
+impl LockInfo {
+    fn is_stale(&self, now: i64) -> bool {
+        now - self.heartbeat_at > STALE_AFTER_SECS
+    }
+
+    fn is_this_process(&self) -> bool {
+        self.host == current_host() && self.pid == std::process::id()
+    }
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another live instance holds the folder.
+    Held(LockInfo),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Held(info) => {
+                let since = chrono::DateTime::from_timestamp(info.acquired_at, 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                write!(
+                    f,
+                    "This working folder is in use by ClipTrim on {} (process {}) since {}",
+                    info.host, info.pid, since
+                )
+            }
+            Self::Io(e) => write!(f, "Could not lock working folder: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for LockError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Exclusive claim on a working folder, held for as long as the value lives.
+/// A background thread keeps the heartbeat fresh; dropping the lock stops it
+/// and removes the lock file.
+pub struct FolderLock {
+    path: PathBuf,
+    token: String,
+    stop: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+}
+
+impl FolderLock {
+    /// Claims the folder. If another instance later takes the lock over,
+    /// e.g. after this one was suspended past `STALE_AFTER_SECS`, the lock
+    /// reports `is_lost` and `on_lost` is called with the new holder.
+    pub fn acquire(
+        cliptrim_dir: &Path,
+        on_lost: impl FnOnce(Option<LockInfo>) + Send + 'static,
+    ) -> Result<Self, LockError> {
+        let path = cliptrim_dir.join(LOCK_FILE);
+        let now = chrono::Utc::now().timestamp();
+        let info = LockInfo {
+            host: current_host(),
+            pid: std::process::id(),
+            acquired_at: now,
+            heartbeat_at: now,
+            token: uuid::Uuid::new_v4().to_string(),
+        };
+        let contents = serde_json::to_vec_pretty(&info).map_err(std::io::Error::other)?;
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(&contents)?;
+                file.sync_all()?;
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                // An unreadable lock is a half-written one from a crash
+                if let Some(existing) = read_lock(&path) {
+                    if !existing.is_stale(now) && !existing.is_this_process() {
+                        return Err(LockError::Held(existing));
+                    }
+                    eprintln!("[lock] taking over lock from {} (pid {})", existing.host, existing.pid);
+                }
+                write_atomic(&path, &info.token, &contents)?;
+                thread::sleep(TAKEOVER_SETTLE);
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // Whoever wrote last owns the lock
+        match read_lock(&path) {
+            Some(current) if current.token == info.token => {}
+            Some(current) => return Err(LockError::Held(current)),
+            None => return Err(std::io::Error::other("the lock file changed while it was being acquired").into()),
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let lost = Arc::new(AtomicBool::new(false));
+        spawn_heartbeat(path.clone(), info.clone(), stop.clone(), lost.clone(), on_lost);
+        Ok(Self {
+            path,
+            token: info.token,
+            stop,
+            lost,
+        })
+    }
+
+    /// Whether another instance has taken the folder over since `acquire`.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for FolderLock {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if read_lock(&self.path).is_some_and(|info| info.token == self.token) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn spawn_heartbeat(
+    path: PathBuf,
+    mut info: LockInfo,
+    stop: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+    on_lost: impl FnOnce(Option<LockInfo>) + Send + 'static,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(HEARTBEAT_INTERVAL);
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let current = read_lock(&path);
+        if current.as_ref().is_none_or(|current| current.token != info.token) {
+            eprintln!("[lock] lost lock on {:?} to another instance", path);
+            lost.store(true, Ordering::SeqCst);
+            on_lost(current);
+            break;
+        }
+        info.heartbeat_at = chrono::Utc::now().timestamp();
+        if let Ok(contents) = serde_json::to_vec_pretty(&info) {
+            if let Err(e) = write_atomic(&path, &info.token, &contents) {
+                eprintln!("[lock] heartbeat failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Replaces the lock file in one step so readers never see it half-written,
+/// which they would take for a crashed holder's lock.
+fn write_atomic(path: &Path, token: &str, contents: &[u8]) -> std::io::Result<()> {
+    let temp = path.with_file_name(format!("{}.{}.tmp", LOCK_FILE, token));
+    let mut file = OpenOptions::new().write(true).create_new(true).open(&temp)?;
+    let written = file.write_all(contents).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written.and_then(|_| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn read_lock(path: &Path) -> Option<LockInfo> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn current_host() -> String {
+    gethostname::gethostname().to_string_lossy().to_string()
+}
@@ -0,0 +1,113 @@
+use crate::ffmpeg::{self, FrameFormat};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+
+/// Upper bound on encoded frame bytes kept in memory.
+const CACHE_CAPACITY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Widths outside this range are clamped; full-size frames pass `None`.
+const MIN_WIDTH: u32 = 16;
+const MAX_WIDTH: u32 = 3840;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FrameKey {
+    clip_id: String,
+    time_ms: i64,
+    width: Option<u32>,
+    format: FrameFormat,
+}
+
+struct CacheEntry {
+    bytes: Arc<Vec<u8>>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<FrameKey, CacheEntry>,
+    /// Keys by the tick they were last used at, oldest first.
+    recency: BTreeMap<u64, FrameKey>,
+    total_bytes: usize,
+    tick: u64,
+}
+
+/// Least-recently-used cache of rendered frames shared by the `/frame`
+/// endpoint and still-image exports.
+#[derive(Default)]
+pub struct FrameCache {
+    inner: Mutex<CacheInner>,
+}
+
+impl FrameCache {
+    fn key(clip_id: &str, time_ms: i64, width: Option<u32>, format: FrameFormat) -> FrameKey {
+        FrameKey {
+            clip_id: clip_id.to_string(),
+            time_ms: time_ms.max(0),
+            width: width.map(|w| w.clamp(MIN_WIDTH, MAX_WIDTH)),
+            format,
+        }
+    }
+
+    /// Returns the frame if it is cached, without rendering it.
+    pub fn cached(&self, clip_id: &str, time_ms: i64, width: Option<u32>, format: FrameFormat) -> Option<Arc<Vec<u8>>> {
+        self.get(&Self::key(clip_id, time_ms, width, format))
+    }
+
+    fn get(&self, key: &FrameKey) -> Option<Arc<Vec<u8>>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let entry = inner.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let bytes = entry.bytes.clone();
+        inner.recency.remove(&previous);
+        inner.recency.insert(tick, key.clone());
+        Some(bytes)
+    }
+
+    fn insert(&self, key: FrameKey, bytes: Arc<Vec<u8>>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let last_used = inner.tick;
+        inner.total_bytes += bytes.len();
+        inner.recency.insert(last_used, key.clone());
+        if let Some(old) = inner.entries.insert(key, CacheEntry { bytes, last_used }) {
+            inner.total_bytes -= old.bytes.len();
+            inner.recency.remove(&old.last_used);
+        }
+
+        while inner.total_bytes > CACHE_CAPACITY_BYTES {
+            let Some((_, oldest)) = inner.recency.pop_first() else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&oldest) {
+                inner.total_bytes -= evicted.bytes.len();
+            }
+        }
+    }
+
+    /// Returns the encoded frame for a clip at `time_ms`, rendering it with
+    /// ffmpeg on a cache miss.
+    pub async fn render(
+        &self,
+        app_handle: &AppHandle,
+        clip_id: &str,
+        source: &Path,
+        time_ms: i64,
+        width: Option<u32>,
+        format: FrameFormat,
+    ) -> std::io::Result<Arc<Vec<u8>>> {
+        let key = Self::key(clip_id, time_ms, width, format);
+        if let Some(bytes) = self.get(&key) {
+            return Ok(bytes);
+        }
+
+        let bytes = Arc::new(
+            ffmpeg::extract_frame(app_handle, source, key.time_ms, key.width, format).await?,
+        );
+        self.insert(key, bytes.clone());
+        Ok(bytes)
+    }
+}
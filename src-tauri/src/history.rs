@@ -0,0 +1,412 @@
+use crate::db::{self, Segment, SegmentOperation};
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Edits of the same kind to the same segment this close together are one
+/// undo step, e.g. typing a label or nudging a marker frame by frame.
+const COALESCE_WINDOW_SECS: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// A change to a clip's segment list, as kept in `segment_operations`.
+#[derive(Debug, Clone)]
+pub enum SegmentEdit {
+    Add { segment: Segment },
+    Delete { segment: Segment },
+    UpdateBounds { segment_id: String, before: Bounds, after: Bounds },
+    UpdateLabel { segment_id: String, before: Option<String>, after: Option<String> },
+    /// Segment IDs in list order.
+    Reorder { before: Vec<String>, after: Vec<String> },
+    /// The whole list, for edits that also changed other segments, such as
+    /// merging or normalizing.
+    Replace { before: Vec<Segment>, after: Vec<Segment> },
+}
+
+impl SegmentEdit {
+    fn inverse(self) -> Self {
+        match self {
+            Self::Add { segment } => Self::Delete { segment },
+            Self::Delete { segment } => Self::Add { segment },
+            Self::UpdateBounds { segment_id, before, after } => Self::UpdateBounds { segment_id, before: after, after: before },
+            Self::UpdateLabel { segment_id, before, after } => Self::UpdateLabel { segment_id, before: after, after: before },
+            Self::Reorder { before, after } => Self::Reorder { before: after, after: before },
+            Self::Replace { before, after } => Self::Replace { before: after, after: before },
+        }
+    }
+
+    fn to_operation(&self) -> serde_json::Result<SegmentOperation> {
+        let (kind, segment_id, before_value, after_value) = match self {
+            Self::Add { segment } => ("add", Some(segment.id.clone()), None, Some(serde_json::to_string(segment)?)),
+            Self::Delete { segment } => ("delete", Some(segment.id.clone()), Some(serde_json::to_string(segment)?), None),
+            Self::UpdateBounds { segment_id, before, after } => (
+                "update_bounds",
+                Some(segment_id.clone()),
+                Some(serde_json::to_string(before)?),
+                Some(serde_json::to_string(after)?),
+            ),
+            Self::UpdateLabel { segment_id, before, after } => (
+                "update_label",
+                Some(segment_id.clone()),
+                Some(serde_json::to_string(before)?),
+                Some(serde_json::to_string(after)?),
+            ),
+            Self::Reorder { before, after } => (
+                "reorder",
+                None,
+                Some(serde_json::to_string(before)?),
+                Some(serde_json::to_string(after)?),
+            ),
+            Self::Replace { before, after } => (
+                "replace",
+                None,
+                Some(serde_json::to_string(before)?),
+                Some(serde_json::to_string(after)?),
+            ),
+        };
+        Ok(SegmentOperation {
+            id: 0,
+            kind: kind.to_string(),
+            segment_id,
+            before_value,
+            after_value,
+            created_at: Utc::now().timestamp(),
+        })
+    }
+
+    fn from_operation(op: &SegmentOperation) -> Result<Self, String> {
+        let segment_id = || op.segment_id.clone().ok_or_else(|| corrupt(op));
+        match op.kind.as_str() {
+            "add" => Ok(Self::Add { segment: value(op, &op.after_value)? }),
+            "delete" => Ok(Self::Delete { segment: value(op, &op.before_value)? }),
+            "update_bounds" => Ok(Self::UpdateBounds {
+                segment_id: segment_id()?,
+                before: value(op, &op.before_value)?,
+                after: value(op, &op.after_value)?,
+            }),
+            "update_label" => Ok(Self::UpdateLabel {
+                segment_id: segment_id()?,
+                before: value(op, &op.before_value)?,
+                after: value(op, &op.after_value)?,
+            }),
+            "reorder" => Ok(Self::Reorder {
+                before: value(op, &op.before_value)?,
+                after: value(op, &op.after_value)?,
+            }),
+            "replace" => Ok(Self::Replace {
+                before: value(op, &op.before_value)?,
+                after: value(op, &op.after_value)?,
+            }),
+            _ => Err(corrupt(op)),
+        }
+    }
+}
+
+fn value<T: DeserializeOwned>(op: &SegmentOperation, json: &Option<String>) -> Result<T, String> {
+    let json = json.as_deref().ok_or_else(|| corrupt(op))?;
+    serde_json::from_str(json).map_err(|_| corrupt(op))
+}
+
+fn corrupt(op: &SegmentOperation) -> String {
+    format!("Segment history entry {} ({}) is unreadable", op.id, op.kind)
+}
+
+/// Logs an edit that was just applied and clears the redo stack. Runs inside
+/// the caller's transaction.
+pub fn record(conn: &Connection, clip_id: &str, edit: &SegmentEdit) -> Result<(), String> {
+    db::delete_undone_segment_operations(conn, clip_id).map_err(|e| e.to_string())?;
+    let op = edit.to_operation().map_err(|e| e.to_string())?;
+
+    if matches!(edit, SegmentEdit::UpdateBounds { .. } | SegmentEdit::UpdateLabel { .. }) {
+        let last = db::get_last_applied_segment_operation(conn, clip_id).map_err(|e| e.to_string())?;
+        if let Some(last) = last.filter(|last| {
+            last.kind == op.kind
+                && last.segment_id == op.segment_id
+                && op.created_at - last.created_at <= COALESCE_WINDOW_SECS
+        }) {
+            return db::update_segment_operation_after(conn, last.id, op.after_value.as_deref(), op.created_at)
+                .map_err(|e| e.to_string());
+        }
+    }
+    db::insert_segment_operation(conn, clip_id, &op).map_err(|e| e.to_string())
+}
+
+/// Reverts the clip's most recent edit that is not already undone.
+pub fn undo(conn: &Connection, clip_id: &str) -> Result<(), String> {
+    let op = db::get_last_applied_segment_operation(conn, clip_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Nothing to undo")?;
+    apply(conn, clip_id, SegmentEdit::from_operation(&op)?.inverse())?;
+    db::set_segment_operation_undone(conn, op.id, true).map_err(|e| e.to_string())
+}
+
+/// Reapplies the clip's most recently undone edit.
+pub fn redo(conn: &Connection, clip_id: &str) -> Result<(), String> {
+    let op = db::get_first_undone_segment_operation(conn, clip_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Nothing to redo")?;
+    apply(conn, clip_id, SegmentEdit::from_operation(&op)?)?;
+    db::set_segment_operation_undone(conn, op.id, false).map_err(|e| e.to_string())
+}
+
+/// Export numbers are assigned outside the history, so a snapshot's may be
+/// stale. Segments that still exist keep the number they have now; ones
+/// coming back keep their recorded number unless another segment took it
+/// meanwhile, in which case the next export gives them a new one.
+fn keep_export_numbers(current: &[Segment], segments: &mut [Segment]) {
+    for segment in segments {
+        segment.export_number = match current.iter().find(|s| s.id == segment.id) {
+            Some(existing) => existing.export_number,
+            None => segment
+                .export_number
+                .filter(|number| !current.iter().any(|s| s.export_number == Some(*number))),
+        };
+    }
+}
+
+fn apply(conn: &Connection, clip_id: &str, edit: SegmentEdit) -> Result<(), String> {
+    let changed = match edit {
+        SegmentEdit::Add { mut segment } => {
+            let current = db::get_segments(conn, clip_id).map_err(|e| e.to_string())?;
+            keep_export_numbers(&current, std::slice::from_mut(&mut segment));
+            // Put it back where it was; indices were closed up when it went
+            let mut ids: Vec<String> = current.into_iter().map(|s| s.id).collect();
+            ids.insert((segment.idx.max(0) as usize).min(ids.len()), segment.id.clone());
+            db::insert_segment(conn, &segment).map_err(|e| e.to_string())?;
+            db::set_segment_order(conn, clip_id, &ids).map_err(|e| e.to_string())?;
+            1
+        }
+        SegmentEdit::Delete { segment } => {
+            db::delete_segment(conn, &segment.id).map_err(|e| e.to_string())?;
+            db::renumber_segments(conn, clip_id).map_err(|e| e.to_string())?;
+            1
+        }
+        SegmentEdit::UpdateBounds { segment_id, after, .. } => {
+            db::update_segment_bounds(conn, &segment_id, after.start_ms, after.end_ms).map_err(|e| e.to_string())?
+        }
+        SegmentEdit::UpdateLabel { segment_id, after, .. } => {
+            db::update_segment_label(conn, &segment_id, after.as_deref()).map_err(|e| e.to_string())?
+        }
+        SegmentEdit::Reorder { after, .. } => {
+            db::set_segment_order(conn, clip_id, &after).map_err(|e| e.to_string())?;
+            1
+        }
+        SegmentEdit::Replace { mut after, .. } => {
+            let current = db::get_segments(conn, clip_id).map_err(|e| e.to_string())?;
+            keep_export_numbers(&current, &mut after);
+            db::replace_segments(conn, clip_id, &after).map_err(|e| e.to_string())?;
+            1
+        }
+    };
+    if changed == 0 {
+        return Err("The segment this edit refers to no longer exists".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Clip, ClipStatus, ProxyStatus};
+
+    const CLIP: &str = "clip";
+
+    fn open() -> Connection {
+        let conn = db::init_db(":memory:").unwrap();
+        db::insert_clip(
+            &conn,
+            &Clip {
+                id: CLIP.to_string(),
+                original_name: "clip.mp4".to_string(),
+                relative_path: "clip.mp4".to_string(),
+                backup_path: "BACKUP/clip.mp4".to_string(),
+                status: ClipStatus::InProgress,
+                created_at: 0,
+                proxy_status: ProxyStatus::NotNeeded,
+                proxy_path: None,
+                container: None,
+                video_codec: None,
+                audio_codec: None,
+                size_bytes: None,
+                fingerprint: None,
+                content_hash: None,
+                duration_ms: Some(60_000),
+                fps: Some(25.0),
+            },
+        )
+        .unwrap();
+        conn
+    }
+
+    fn seg(id: &str, idx: i64, start_ms: i64, end_ms: i64) -> Segment {
+        Segment {
+            id: id.to_string(),
+            clip_id: CLIP.to_string(),
+            idx,
+            start_ms,
+            end_ms,
+            label: None,
+            export_number: None,
+        }
+    }
+
+    fn add(conn: &Connection, segment: Segment) {
+        db::insert_segment(conn, &segment).unwrap();
+        record(conn, CLIP, &SegmentEdit::Add { segment }).unwrap();
+    }
+
+    fn delete(conn: &Connection, id: &str) {
+        let segment = db::get_segment(conn, id).unwrap().unwrap();
+        db::delete_segment(conn, id).unwrap();
+        db::renumber_segments(conn, CLIP).unwrap();
+        record(conn, CLIP, &SegmentEdit::Delete { segment }).unwrap();
+    }
+
+    /// (id, start, end) in list order.
+    fn listed(conn: &Connection) -> Vec<(String, i64, i64)> {
+        db::get_segments(conn, CLIP)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.id, s.start_ms, s.end_ms))
+            .collect()
+    }
+
+    fn numbers(conn: &Connection) -> Vec<(String, Option<i64>)> {
+        db::get_segments(conn, CLIP)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.id, s.export_number))
+            .collect()
+    }
+
+    fn entry(id: &str, start_ms: i64, end_ms: i64) -> (String, i64, i64) {
+        (id.to_string(), start_ms, end_ms)
+    }
+
+    #[test]
+    fn undo_and_redo_an_add() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        add(&conn, seg("b", 1, 2000, 3000));
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000)]);
+        redo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000), entry("b", 2000, 3000)]);
+        assert_eq!(redo(&conn, CLIP), Err("Nothing to redo".to_string()));
+    }
+
+    #[test]
+    fn undoing_a_delete_puts_the_segment_back_in_place() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        add(&conn, seg("b", 1, 2000, 3000));
+        add(&conn, seg("c", 2, 4000, 5000));
+        delete(&conn, "b");
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000), entry("c", 4000, 5000)]);
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(
+            listed(&conn),
+            vec![entry("a", 0, 1000), entry("b", 2000, 3000), entry("c", 4000, 5000)]
+        );
+        redo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000), entry("c", 4000, 5000)]);
+    }
+
+    #[test]
+    fn undo_and_redo_a_bounds_change() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        db::update_segment_bounds(&conn, "a", 500, 1500).unwrap();
+        record(
+            &conn,
+            CLIP,
+            &SegmentEdit::UpdateBounds {
+                segment_id: "a".to_string(),
+                before: Bounds { start_ms: 0, end_ms: 1000 },
+                after: Bounds { start_ms: 500, end_ms: 1500 },
+            },
+        )
+        .unwrap();
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000)]);
+        redo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 500, 1500)]);
+    }
+
+    #[test]
+    fn undo_and_redo_a_reorder() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        add(&conn, seg("b", 1, 2000, 3000));
+        let before = vec!["a".to_string(), "b".to_string()];
+        let after = vec!["b".to_string(), "a".to_string()];
+        db::set_segment_order(&conn, CLIP, &after).unwrap();
+        record(&conn, CLIP, &SegmentEdit::Reorder { before, after }).unwrap();
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("a", 0, 1000), entry("b", 2000, 3000)]);
+        redo(&conn, CLIP).unwrap();
+        assert_eq!(listed(&conn), vec![entry("b", 2000, 3000), entry("a", 0, 1000)]);
+    }
+
+    #[test]
+    fn replaying_a_replace_keeps_assigned_export_numbers() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        add(&conn, seg("b", 1, 800, 2000));
+        // Merged before either was exported, so the snapshots carry no numbers
+        let before = db::get_segments(&conn, CLIP).unwrap();
+        let after = vec![seg("a", 0, 0, 2000)];
+        db::replace_segments(&conn, CLIP, &after).unwrap();
+        record(&conn, CLIP, &SegmentEdit::Replace { before, after }).unwrap();
+        db::assign_export_numbers(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("a".to_string(), Some(1))]);
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("a".to_string(), Some(1)), ("b".to_string(), None)]);
+        db::assign_export_numbers(&conn, CLIP).unwrap();
+
+        redo(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("a".to_string(), Some(1))]);
+        assert_eq!(listed(&conn), vec![entry("a", 0, 2000)]);
+    }
+
+    #[test]
+    fn a_restored_segment_gives_up_a_number_taken_since() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        db::assign_export_numbers(&conn, CLIP).unwrap();
+        add(&conn, seg("b", 1, 2000, 3000));
+        delete(&conn, "a");
+        // With `a` gone, `b` is exported as number 1
+        db::assign_export_numbers(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("b".to_string(), Some(1))]);
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("a".to_string(), None), ("b".to_string(), Some(1))]);
+        assert_eq!(
+            db::assign_export_numbers(&conn, CLIP).unwrap()[0].export_number,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn a_restored_segment_keeps_its_free_number() {
+        let conn = open();
+        add(&conn, seg("a", 0, 0, 1000));
+        add(&conn, seg("b", 1, 2000, 3000));
+        db::assign_export_numbers(&conn, CLIP).unwrap();
+        delete(&conn, "a");
+
+        undo(&conn, CLIP).unwrap();
+        assert_eq!(numbers(&conn), vec![("a".to_string(), Some(1)), ("b".to_string(), Some(2))]);
+    }
+}
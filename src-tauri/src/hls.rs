@@ -0,0 +1,189 @@
+use crate::ffmpeg;
+use crate::pool::WorkerPool;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+use tauri::AppHandle;
+
+/// Length of each HLS chunk in seconds.
+const SEGMENT_SECS: f64 = 6.0;
+
+/// Total size the `.cliptrim/hls/` cache may grow to before the least
+/// recently used clips are evicted.
+const CACHE_LIMIT_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+const PLAYLIST_NAME: &str = "index.m3u8";
+
+/// Empty file whose mtime records when a clip's cache was last used.
+const LAST_USED_MARKER: &str = ".last_used";
+
+/// How many playlists or chunks may be generated at once. Generation runs on
+/// its own threads so the media server keeps serving while ffmpeg works.
+const GENERATION_THREADS: usize = 2;
+
+static GENERATION_POOL: WorkerPool = WorkerPool::new(GENERATION_THREADS);
+
+/// One lock per cache file being generated, so two requests for the same
+/// chunk share one ffmpeg run while other chunks and clips go ahead.
+static IN_FLIGHT: LazyLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = LazyLock::new(Default::default);
+
+/// Runs `job` on the generation pool.
+pub fn spawn_generation(job: impl FnOnce() + Send + 'static) {
+    GENERATION_POOL.spawn(job);
+}
+
+/// Runs `generate` unless `path` exists by the time this caller holds the
+/// file's lock.
+fn generate_once(path: &Path, generate: impl FnOnce() -> Result<()>) -> Result<()> {
+    let lock = IN_FLIGHT.lock().unwrap().entry(path.to_path_buf()).or_default().clone();
+    let _guard = lock.lock().unwrap();
+    let result = if path.exists() { Ok(()) } else { generate() };
+    IN_FLIGHT.lock().unwrap().remove(path);
+    result
+}
+
+/// Parses a chunk file name like `seg_00012.ts` into its index.
+pub fn parse_segment_name(name: &str) -> Option<u32> {
+    name.strip_prefix("seg_")?.strip_suffix(".ts")?.parse().ok()
+}
+
+fn segment_name(index: u32) -> String {
+    format!("seg_{:05}.ts", index)
+}
+
+fn clip_cache_dir(hls_dir: &Path, clip_id: &str) -> PathBuf {
+    hls_dir.join(clip_id)
+}
+
+/// Returns the VOD playlist for a clip, writing it to the cache on first use.
+/// Probing blocks, so call it from `spawn_generation`.
+/// Chunk URIs are relative; `query` (e.g. the session token) is appended to
+/// each so the player can fetch them.
+pub fn playlist(app_handle: &AppHandle, hls_dir: &Path, clip_id: &str, source: &Path, query: &str) -> Result<String> {
+    let dir = clip_cache_dir(hls_dir, clip_id);
+    let playlist_path = dir.join(PLAYLIST_NAME);
+
+    generate_once(&playlist_path, || {
+        let probe = tauri::async_runtime::block_on(ffmpeg::probe_media(app_handle, source))?;
+        fs::create_dir_all(&dir)?;
+        fs::write(&playlist_path, build_playlist(probe.duration_sec))
+    })?;
+    let playlist = fs::read_to_string(&playlist_path)?;
+    touch(&dir);
+
+    Ok(playlist
+        .lines()
+        .map(|line| {
+            if line.starts_with('#') || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{}?{}", line, query)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn build_playlist(duration_sec: f64) -> String {
+    let count = (duration_sec / SEGMENT_SECS).ceil().max(1.0) as u32;
+    let mut playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+        SEGMENT_SECS.ceil() as u32
+    );
+    for i in 0..count {
+        let start = i as f64 * SEGMENT_SECS;
+        let length = (duration_sec - start).min(SEGMENT_SECS);
+        playlist.push_str(&format!("#EXTINF:{:.3},\n{}\n", length, segment_name(i)));
+    }
+    playlist.push_str("#EXT-X-ENDLIST\n");
+    playlist
+}
+
+/// Returns the path of a chunk if it is already cached.
+pub fn cached_segment(hls_dir: &Path, clip_id: &str, index: u32) -> Option<PathBuf> {
+    let dir = clip_cache_dir(hls_dir, clip_id);
+    let path = dir.join(segment_name(index));
+    if !path.exists() {
+        return None;
+    }
+    touch(&dir);
+    Some(path)
+}
+
+/// Returns the path of a cached chunk, transcoding it first if needed.
+/// Blocks on ffmpeg, so call it from `spawn_generation`.
+pub fn segment(app_handle: &AppHandle, hls_dir: &Path, clip_id: &str, source: &Path, index: u32) -> Result<PathBuf> {
+    let dir = clip_cache_dir(hls_dir, clip_id);
+    let path = dir.join(segment_name(index));
+    generate_once(&path, || {
+        fs::create_dir_all(&dir)?;
+        let start_ms = (index as f64 * SEGMENT_SECS * 1000.0) as i64;
+        let length_ms = (SEGMENT_SECS * 1000.0) as i64;
+        tauri::async_runtime::block_on(ffmpeg::export_hls_segment(
+            app_handle, source, &path, start_ms, length_ms,
+        ))?;
+        if let Err(e) = evict(hls_dir, clip_id) {
+            eprintln!("[hls] cache eviction failed: {}", e);
+        }
+        Ok(())
+    })?;
+    touch(&dir);
+    Ok(path)
+}
+
+/// Marks a clip's cache as recently used.
+fn touch(dir: &Path) {
+    let _ = fs::File::create(dir.join(LAST_USED_MARKER)).and_then(|f| f.set_modified(SystemTime::now()));
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Removes whole clip caches, oldest first, until the cache fits the limit.
+/// The clip currently being played is never evicted.
+fn evict(hls_dir: &Path, active_clip_id: &str) -> Result<()> {
+    let mut caches: Vec<(PathBuf, SystemTime, u64)> = fs::read_dir(hls_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| {
+            let path = e.path();
+            let modified = fs::metadata(path.join(LAST_USED_MARKER))
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let size = dir_size(&path);
+            (path, modified, size)
+        })
+        .collect();
+
+    let mut total: u64 = caches.iter().map(|(_, _, size)| size).sum();
+    if total <= CACHE_LIMIT_BYTES {
+        return Ok(());
+    }
+
+    caches.sort_by_key(|(_, modified, _)| *modified);
+    for (path, _, size) in caches {
+        if total <= CACHE_LIMIT_BYTES {
+            break;
+        }
+        if path.file_name().is_some_and(|n| n == active_clip_id) {
+            continue;
+        }
+        fs::remove_dir_all(&path)?;
+        total -= size;
+        eprintln!("[hls] evicted {:?} ({} bytes)", path, size);
+    }
+    Ok(())
+}
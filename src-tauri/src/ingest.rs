@@ -0,0 +1,170 @@
+use crate::commands::AppState;
+use crate::db::{self, Clip, ClipStatus, ProxyStatus};
+use crate::ffmpeg;
+use crate::fingerprint;
+use crate::proxy::ProxyQueue;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// Files written to more recently than this are assumed to still be
+/// recording or copying and are left for a later scan.
+pub const SETTLE_TIME: Duration = Duration::from_secs(3);
+
+fn is_settled(file: &PathBuf) -> bool {
+    std::fs::metadata(file)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= SETTLE_TIME)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct IngestSummary {
+    pub added: usize,
+    pub renamed: usize,
+    /// Files skipped because they were modified too recently; callers that
+    /// watch IN retry them later.
+    pub unsettled: Vec<PathBuf>,
+}
+
+/// A not-yet-opened clip whose IN file is gone and whose content matches
+/// `fingerprint`, i.e. the file was renamed or moved within IN.
+fn find_renamed(conn: &rusqlite::Connection, fingerprint: &str) -> Option<Clip> {
+    db::get_clips_by_fingerprint(conn, fingerprint)
+        .ok()?
+        .into_iter()
+        .find(|c| c.status == ClipStatus::New && !Path::new(&c.backup_path).exists())
+}
+
+/// Adds the given IN files to the DB as `new` clips. Files already known,
+/// still being written, unreadable by ffprobe or without a video stream are
+/// skipped; files whose content matches a vanished `new` clip are treated
+/// as a rename of that clip.
+pub async fn ingest_files(app_handle: &AppHandle, files: Vec<PathBuf>) -> Result<IngestSummary, String> {
+    let state = app_handle.state::<AppState>();
+
+    let (files, unsettled): (Vec<PathBuf>, Vec<PathBuf>) = files.into_iter().partition(is_settled);
+
+    // Find files that are not in the DB yet
+    let new_files: Vec<(String, PathBuf)> = {
+        let wd_guard = state.work_dir.lock().unwrap();
+        let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+
+        files
+            .into_iter()
+            .filter_map(|file| Some((wd.relative_in_path(&file)?, file)))
+            .filter(|(relative_path, _)| matches!(db::get_clip_by_relative_path(conn, relative_path), Ok(None)))
+            .collect()
+    };
+
+    // Fingerprint and probe outside the locks
+    let mut probed = Vec::new();
+    for (relative_path, file) in new_files {
+        let (size, fingerprint) = match fingerprint::quick_fingerprint(&file) {
+            Ok(fp) => fp,
+            Err(e) => {
+                eprintln!("[ingest] skipping {:?}: {}", file, e);
+                continue;
+            }
+        };
+        match ffmpeg::probe_media(app_handle, &file).await {
+            Ok(probe) if probe.video.is_some() => probed.push((relative_path, file, size, fingerprint, probe)),
+            Ok(_) => eprintln!("[ingest] skipping {:?}: no video stream", file),
+            Err(e) => eprintln!("[ingest] skipping {:?}: {}", file, e),
+        }
+    }
+
+    let mut summary = IngestSummary {
+        unsettled,
+        ..IngestSummary::default()
+    };
+    let mut added = Vec::new();
+    {
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        // All or nothing, so a failed insert does not leave half a batch
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        let conn: &rusqlite::Connection = &tx;
+
+        for (relative_path, file, size, fingerprint, probe) in probed {
+            // Another scan may have raced us while probing.
+            if !matches!(db::get_clip_by_relative_path(conn, &relative_path), Ok(None)) {
+                continue;
+            }
+            let original_name = file.file_name().unwrap().to_string_lossy().to_string();
+            let backup_path = file.to_string_lossy().to_string(); // Temporarily store IN path
+
+            if let Some(clip) = find_renamed(conn, &fingerprint) {
+                db::update_clip_location(conn, &clip.id, &original_name, &relative_path, &backup_path)
+                    .map_err(|e| e.to_string())?;
+                eprintln!("[ingest] {} renamed to {}", clip.relative_path, relative_path);
+                summary.renamed += 1;
+                continue;
+            }
+
+            let duration_ms = probe.duration_ms();
+            let clip = Clip {
+                id: Uuid::new_v4().to_string(),
+                original_name,
+                relative_path,
+                backup_path,
+                status: ClipStatus::New,
+                created_at: Utc::now().timestamp(),
+                proxy_status: ProxyStatus::Unchecked,
+                proxy_path: None,
+                container: Some(probe.format_name),
+                fps: probe.video.as_ref().map(|v| v.fps),
+                video_codec: probe.video.map(|v| v.codec),
+                audio_codec: probe.audio_codec,
+                size_bytes: Some(size as i64),
+                fingerprint: Some(fingerprint),
+                content_hash: None,
+                duration_ms,
+            };
+            db::insert_clip(conn, &clip).map_err(|e| e.to_string())?;
+            added.push(clip.id);
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    summary.added = added.len();
+
+    let proxies = app_handle.state::<ProxyQueue>();
+    for clip_id in &added {
+        proxies.enqueue(clip_id);
+    }
+    if summary.added > 0 {
+        fingerprint::spawn_hash_pass(app_handle.clone());
+    }
+    Ok(summary)
+}
+
+/// Drops clips whose file disappeared from IN before they were ever opened.
+/// Opened clips live in BACKUP and are not affected. Returns the number removed.
+pub fn remove_vanished(app_handle: &AppHandle, files: &[PathBuf]) -> Result<usize, String> {
+    let state = app_handle.state::<AppState>();
+    let wd_guard = state.work_dir.lock().unwrap();
+    let wd = wd_guard.as_ref().ok_or("Working folder not set")?;
+
+    let db_guard = state.db.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+
+    let mut removed = 0;
+    for file in files.iter().filter(|f| !f.exists()) {
+        let Some(relative_path) = wd.relative_in_path(file) else {
+            continue;
+        };
+        if let Ok(Some(clip)) = db::get_clip_by_relative_path(conn, &relative_path) {
+            if clip.status == ClipStatus::New && Path::new(&clip.backup_path) == file.as_path() {
+                db::delete_clip(conn, &clip.id).map_err(|e| e.to_string())?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
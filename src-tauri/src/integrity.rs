@@ -0,0 +1,204 @@
+use crate::db::{self, Clip, ClipStatus, StatusError};
+use crate::file_manager::{self, WorkingDirectory};
+use crate::fingerprint;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct MissingFile {
+    pub clip_id: String,
+    pub relative_path: String,
+    pub path: String,
+    pub status: ClipStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SizeMismatch {
+    pub clip_id: String,
+    pub path: String,
+    pub expected_bytes: i64,
+    pub actual_bytes: u64,
+}
+
+/// Everything that no longer lines up between `cliptrim.db` and the disk.
+#[derive(Debug, Default, Serialize)]
+pub struct IntegrityReport {
+    /// Clips whose original is not at `backup_path`.
+    pub missing: Vec<MissingFile>,
+    /// Originals whose size changed since ingest.
+    pub size_mismatches: Vec<SizeMismatch>,
+    /// Files in BACKUP no clip points to.
+    pub orphaned_backups: Vec<String>,
+    /// Files in OUT that were not exported from any known clip.
+    pub orphaned_outputs: Vec<String>,
+    /// Recorded exports that were deleted or moved.
+    pub missing_outputs: Vec<String>,
+    /// IDs of segments whose clip no longer exists.
+    pub orphaned_segments: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RepairOptions {
+    /// Look for missing originals in IN and BACKUP by size/fingerprint or name.
+    pub relink: bool,
+    /// Set clips that could not be relinked to `missing`.
+    pub mark_missing: bool,
+    pub drop_orphaned_segments: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Relinked {
+    pub clip_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    pub relinked: Vec<Relinked>,
+    pub marked_missing: Vec<String>,
+    pub removed_segments: usize,
+}
+
+pub fn check(wd: &WorkingDirectory, conn: &Connection) -> rusqlite::Result<IntegrityReport> {
+    let clips = db::get_clips(conn)?;
+    let mut report = IntegrityReport::default();
+
+    // An empty backup path means retention cleanup deleted the original.
+    for clip in clips.iter().filter(|c| !c.backup_path.is_empty()) {
+        match std::fs::metadata(&clip.backup_path) {
+            Ok(meta) => {
+                if let Some(expected) = clip.size_bytes {
+                    if meta.len() != expected as u64 {
+                        report.size_mismatches.push(SizeMismatch {
+                            clip_id: clip.id.clone(),
+                            path: clip.backup_path.clone(),
+                            expected_bytes: expected,
+                            actual_bytes: meta.len(),
+                        });
+                    }
+                }
+            }
+            Err(_) => report.missing.push(MissingFile {
+                clip_id: clip.id.clone(),
+                relative_path: clip.relative_path.clone(),
+                path: clip.backup_path.clone(),
+                status: clip.status,
+            }),
+        }
+    }
+
+    let referenced: HashSet<PathBuf> = clips.iter().map(|c| PathBuf::from(&c.backup_path)).collect();
+    report.orphaned_backups = file_manager::walk_files(&wd.backup_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| !referenced.contains(f))
+        .map(|f| f.to_string_lossy().to_string())
+        .collect();
+
+    let exports = db::get_all_export_paths(conn)?;
+    let exported: HashSet<PathBuf> = exports.iter().map(PathBuf::from).collect();
+    report.missing_outputs = exports.into_iter().filter(|p| !Path::new(p).exists()).collect();
+    // Outputs from before exports were recorded are matched by naming scheme.
+    let prefixes: Vec<String> = clips.iter().map(|c| format!("{}__", file_stem(&c.original_name))).collect();
+    report.orphaned_outputs = file_manager::walk_files(&wd.out_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| !exported.contains(f))
+        .filter(|f| {
+            let name = f.file_name().unwrap_or_default().to_string_lossy();
+            !prefixes.iter().any(|prefix| name.starts_with(prefix.as_str()))
+        })
+        .map(|f| f.to_string_lossy().to_string())
+        .collect();
+
+    report.orphaned_segments = db::get_orphaned_segment_ids(conn)?;
+    Ok(report)
+}
+
+pub fn repair(wd: &WorkingDirectory, conn: &Connection, options: &RepairOptions) -> Result<RepairReport, String> {
+    let clips = db::get_clips(conn).map_err(|e| e.to_string())?;
+    let mut report = RepairReport::default();
+
+    let missing: Vec<&Clip> = clips
+        .iter()
+        .filter(|c| !c.backup_path.is_empty() && !Path::new(&c.backup_path).exists())
+        .collect();
+    if !missing.is_empty() {
+        let referenced: HashSet<PathBuf> = clips.iter().map(|c| PathBuf::from(&c.backup_path)).collect();
+        let mut candidates: Vec<PathBuf> = file_manager::walk_files(&wd.in_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .chain(file_manager::walk_files(&wd.backup_dir).unwrap_or_default())
+            .filter(|f| !referenced.contains(f))
+            .collect();
+
+        for clip in missing {
+            let found = if options.relink { find_relocated(clip, &candidates) } else { None };
+            match found {
+                Some(path) => {
+                    relink(wd, conn, clip, &path).map_err(|e| e.to_string())?;
+                    candidates.retain(|c| c != &path);
+                    report.relinked.push(Relinked {
+                        clip_id: clip.id.clone(),
+                        path: path.to_string_lossy().to_string(),
+                    });
+                }
+                None if options.mark_missing && clip.status != ClipStatus::Missing => {
+                    db::update_clip_status(conn, &clip.id, ClipStatus::Missing).map_err(|e| e.to_string())?;
+                    report.marked_missing.push(clip.id.clone());
+                }
+                None => {}
+            }
+        }
+    }
+
+    if options.drop_orphaned_segments {
+        report.removed_segments = db::delete_orphaned_segments(conn).map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
+
+/// Finds where a clip's original went. Clips with a recorded size and
+/// fingerprint are matched by content; older clips only by a unique file name.
+fn find_relocated(clip: &Clip, candidates: &[PathBuf]) -> Option<PathBuf> {
+    if let (Some(size), Some(expected)) = (clip.size_bytes, clip.fingerprint.as_deref()) {
+        return candidates
+            .iter()
+            .filter(|c| std::fs::metadata(c).is_ok_and(|m| m.len() == size as u64))
+            .find(|c| fingerprint::quick_fingerprint(c).is_ok_and(|(_, fp)| fp == expected))
+            .cloned();
+    }
+
+    let mut by_name = candidates
+        .iter()
+        .filter(|c| c.file_name().is_some_and(|n| n.to_string_lossy() == clip.original_name));
+    match (by_name.next(), by_name.next()) {
+        (Some(only), None) => Some(only.clone()),
+        _ => None,
+    }
+}
+
+fn relink(wd: &WorkingDirectory, conn: &Connection, clip: &Clip, path: &Path) -> Result<(), StatusError> {
+    let path_str = path.to_string_lossy();
+    let in_backup = wd.is_in_backup(path);
+    if !in_backup && matches!(clip.status, ClipStatus::New | ClipStatus::Missing) {
+        if let Some(relative_path) = wd.relative_in_path(path) {
+            // Still waiting in IN, possibly under a new name.
+            let original_name = path.file_name().unwrap_or_default().to_string_lossy();
+            return db::reset_clip_to_new(conn, &clip.id, &original_name, &relative_path, &path_str);
+        }
+    }
+
+    db::update_clip_backup_path(conn, &clip.id, &path_str)?;
+    // An original found in BACKUP has been opened, whatever the DB said.
+    if in_backup && matches!(clip.status, ClipStatus::New | ClipStatus::Missing) {
+        db::update_clip_status(conn, &clip.id, ClipStatus::InProgress)?;
+    }
+    Ok(())
+}
+
+fn file_stem(name: &str) -> String {
+    Path::new(name).file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
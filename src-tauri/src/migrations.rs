@@ -0,0 +1,404 @@
+use rusqlite::{Connection, Transaction};
+use std::fmt;
+use std::path::Path;
+
+/// Schema changes in order. Migration `i` takes a database from
+/// `user_version` `i` to `i + 1` and runs in its own transaction.
+/// Never edit a released migration; append a new one.
+const MIGRATIONS: &[fn(&Transaction) -> rusqlite::Result<()>] = &[v1_baseline, v2_status_history, v3_segment_order, v4_segment_operations, v5_clip_timing];
+
+/// `user_version` of a database fully migrated by this build.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// The pre-migration copy could not be written; nothing was changed.
+    Backup(rusqlite::Error),
+    /// The database was written by a newer ClipTrim.
+    NewerSchema { found: i64, supported: i64 },
+    /// A read-only instance found a database the lock holder has not upgraded yet.
+    OutdatedSchema { found: i64, supported: i64 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "Database error: {}", e),
+            Self::Backup(e) => write!(f, "Could not back up the database before upgrading it: {}", e),
+            Self::NewerSchema { found, supported } => write!(
+                f,
+                "This working folder was opened by a newer version of ClipTrim (database version {}, this version supports up to {}). Please update ClipTrim.",
+                found, supported
+            ),
+            Self::OutdatedSchema { found, supported } => write!(
+                f,
+                "The database of this working folder is at version {} and needs upgrading to {}, which a read-only instance cannot do",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+fn user_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the database up to `SCHEMA_VERSION`. A database with existing
+/// tables is first copied to `cliptrim.db.v<N>.bak` next to it.
+pub fn migrate(conn: &mut Connection, db_path: &Path) -> Result<(), MigrationError> {
+    let version = user_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(MigrationError::NewerSchema { found: version, supported: SCHEMA_VERSION });
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        let backup_path = db_path.with_extension(format!("db.v{}.bak", version));
+        let _ = std::fs::remove_file(&backup_path);
+        conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy()])
+            .map_err(MigrationError::Backup)?;
+        eprintln!("[db] backed up schema v{} to {:?}", version, backup_path);
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", index as i64 + 1)?;
+        tx.commit()?;
+        eprintln!("[db] migrated to schema v{}", index + 1);
+    }
+    Ok(())
+}
+
+/// For connections that must not migrate: fails unless the database is at
+/// exactly `SCHEMA_VERSION`.
+pub fn check_version(conn: &Connection) -> Result<(), MigrationError> {
+    let version = user_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(MigrationError::NewerSchema { found: version, supported: SCHEMA_VERSION });
+    }
+    if version < SCHEMA_VERSION {
+        return Err(MigrationError::OutdatedSchema { found: version, supported: SCHEMA_VERSION });
+    }
+    Ok(())
+}
+
+/// Adds a column to an existing table if it is missing. Only needed by the
+/// baseline, which has to cope with every pre-versioning schema.
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
+/// The schema as it stood when versioning was introduced. Databases from
+/// before then may have any subset of it, so every step is idempotent.
+fn v1_baseline(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS clips (
+            id TEXT PRIMARY KEY,
+            original_name TEXT NOT NULL,
+            backup_path TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS segments (
+            id TEXT PRIMARY KEY,
+            clip_id TEXT NOT NULL,
+            idx INTEGER NOT NULL,
+            start_ms INTEGER NOT NULL,
+            end_ms INTEGER NOT NULL,
+            label TEXT,
+            FOREIGN KEY(clip_id) REFERENCES clips(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS exports (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            clip_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY(clip_id) REFERENCES clips(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    ensure_column(tx, "clips", "proxy_status", "TEXT NOT NULL DEFAULT 'unchecked'")?;
+    ensure_column(tx, "clips", "proxy_path", "TEXT")?;
+    ensure_column(tx, "clips", "container", "TEXT")?;
+    ensure_column(tx, "clips", "video_codec", "TEXT")?;
+    ensure_column(tx, "clips", "audio_codec", "TEXT")?;
+    // Clips from before recursive scanning all sat at the top of IN.
+    ensure_column(tx, "clips", "relative_path", "TEXT NOT NULL DEFAULT ''")?;
+    tx.execute("UPDATE clips SET relative_path = original_name WHERE relative_path = ''", [])?;
+    tx.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_clips_relative_path ON clips(relative_path)",
+        [],
+    )?;
+    ensure_column(tx, "clips", "size_bytes", "INTEGER")?;
+    ensure_column(tx, "clips", "fingerprint", "TEXT")?;
+    ensure_column(tx, "clips", "content_hash", "TEXT")?;
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_clips_fingerprint ON clips(fingerprint)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn v2_status_history(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE clip_status_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            clip_id TEXT NOT NULL,
+            from_status TEXT,
+            to_status TEXT NOT NULL,
+            changed_at INTEGER NOT NULL,
+            FOREIGN KEY(clip_id) REFERENCES clips(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX idx_clip_status_history_clip ON clip_status_history(clip_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn v3_segment_order(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE segments ADD COLUMN export_number INTEGER", [])?;
+    // Deleting used to leave gaps and duplicate indices behind
+    tx.execute(
+        "UPDATE segments SET idx = (
+            SELECT ranked.pos FROM (
+                SELECT id, ROW_NUMBER() OVER (PARTITION BY clip_id ORDER BY idx, rowid) - 1 AS pos
+                FROM segments
+            ) AS ranked
+            WHERE ranked.id = segments.id
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn v4_segment_operations(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE segment_operations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            clip_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            segment_id TEXT,
+            before_value TEXT,
+            after_value TEXT,
+            undone INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY(clip_id) REFERENCES clips(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    tx.execute(
+        "CREATE INDEX idx_segment_operations_clip ON segment_operations(clip_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn v5_clip_timing(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute("ALTER TABLE clips ADD COLUMN duration_ms INTEGER", [])?;
+    tx.execute("ALTER TABLE clips ADD COLUMN fps REAL", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).unwrap();
+        let names = stmt.query_map([], |row| row.get(1)).unwrap();
+        names.map(|name| name.unwrap()).collect()
+    }
+
+    fn table_exists(conn: &Connection, table: &str) -> bool {
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn scratch_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cliptrim-{}.db", uuid::Uuid::new_v4()))
+    }
+
+    fn assert_current_schema(conn: &Connection) {
+        assert_eq!(user_version(conn).unwrap(), SCHEMA_VERSION);
+        let clip_columns = columns(conn, "clips");
+        for column in [
+            "proxy_status",
+            "proxy_path",
+            "container",
+            "video_codec",
+            "audio_codec",
+            "relative_path",
+            "size_bytes",
+            "fingerprint",
+            "content_hash",
+            "duration_ms",
+            "fps",
+        ] {
+            assert!(clip_columns.iter().any(|c| c == column), "clips.{} missing", column);
+        }
+        assert!(columns(conn, "segments").iter().any(|c| c == "export_number"));
+        assert!(table_exists(conn, "clip_status_history"));
+        assert!(table_exists(conn, "segment_operations"));
+    }
+
+    #[test]
+    fn creates_the_schema_in_an_empty_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let path = scratch_path();
+        migrate(&mut conn, &path).unwrap();
+        assert_current_schema(&conn);
+        // Nothing worth backing up
+        assert!(!path.with_extension("db.v0.bak").exists());
+        check_version(&conn).unwrap();
+    }
+
+    #[test]
+    fn upgrades_a_pre_versioning_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE clips (
+                id TEXT PRIMARY KEY,
+                original_name TEXT NOT NULL,
+                backup_path TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                proxy_status TEXT NOT NULL DEFAULT 'unchecked'
+            );
+            CREATE TABLE segments (
+                id TEXT PRIMARY KEY,
+                clip_id TEXT NOT NULL,
+                idx INTEGER NOT NULL,
+                start_ms INTEGER NOT NULL,
+                end_ms INTEGER NOT NULL,
+                label TEXT
+            );
+            INSERT INTO clips (id, original_name, backup_path, status, created_at)
+                VALUES ('c1', 'a.mp4', 'BACKUP/a.mp4', 'new', 1);
+            INSERT INTO segments VALUES ('s1', 'c1', 0, 0, 1000, NULL);
+            INSERT INTO segments VALUES ('s2', 'c1', 3, 1000, 2000, NULL);
+            INSERT INTO segments VALUES ('s3', 'c1', 3, 2000, 3000, NULL);",
+        )
+        .unwrap();
+
+        let path = scratch_path();
+        migrate(&mut conn, &path).unwrap();
+        assert_current_schema(&conn);
+
+        let backup_path = path.with_extension("db.v0.bak");
+        assert!(backup_path.exists());
+        std::fs::remove_file(&backup_path).unwrap();
+
+        let relative_path: String = conn
+            .query_row("SELECT relative_path FROM clips WHERE id = 'c1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(relative_path, "a.mp4");
+        let mut stmt = conn.prepare("SELECT id, idx FROM segments ORDER BY idx").unwrap();
+        let order: Vec<(String, i64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            order,
+            vec![("s1".to_string(), 0), ("s2".to_string(), 1), ("s3".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn baseline_is_idempotent_on_an_existing_baseline() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        v1_baseline(&tx).unwrap();
+        tx.execute(
+            "INSERT INTO clips (id, original_name, backup_path, status, created_at, relative_path)
+                VALUES ('c1', 'a.mp4', 'BACKUP/day1/a.mp4', 'new', 1, 'day1/a.mp4')",
+            [],
+        )
+        .unwrap();
+        tx.commit().unwrap();
+        let before = columns(&conn, "clips");
+
+        let tx = conn.transaction().unwrap();
+        v1_baseline(&tx).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(columns(&conn, "clips"), before);
+        let relative_path: String = conn
+            .query_row("SELECT relative_path FROM clips WHERE id = 'c1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(relative_path, "day1/a.mp4");
+    }
+
+    #[test]
+    fn continues_from_a_partly_migrated_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        v1_baseline(&tx).unwrap();
+        v2_status_history(&tx).unwrap();
+        tx.pragma_update(None, "user_version", 2).unwrap();
+        tx.commit().unwrap();
+        assert!(matches!(
+            check_version(&conn),
+            Err(MigrationError::OutdatedSchema { found: 2, supported: SCHEMA_VERSION })
+        ));
+
+        let path = scratch_path();
+        migrate(&mut conn, &path).unwrap();
+        assert_current_schema(&conn);
+        std::fs::remove_file(path.with_extension("db.v2.bak")).unwrap();
+    }
+
+    #[test]
+    fn refuses_a_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        let found = SCHEMA_VERSION + 1;
+        assert!(matches!(
+            migrate(&mut conn, &scratch_path()),
+            Err(MigrationError::NewerSchema { found: f, supported: SCHEMA_VERSION }) if f == found
+        ));
+        assert!(matches!(
+            check_version(&conn),
+            Err(MigrationError::NewerSchema { found: f, .. }) if f == found
+        ));
+        assert_eq!(user_version(&conn).unwrap(), found);
+    }
+}
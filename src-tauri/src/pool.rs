@@ -0,0 +1,40 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed number of threads for blocking work such as ffmpeg runs, started
+/// on first use. Jobs beyond the thread count wait their turn in order.
+pub struct WorkerPool {
+    threads: usize,
+    queue: OnceLock<Sender<Job>>,
+}
+
+impl WorkerPool {
+    pub const fn new(threads: usize) -> Self {
+        Self {
+            threads,
+            queue: OnceLock::new(),
+        }
+    }
+
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        let queue = self.queue.get_or_init(|| {
+            let (tx, rx) = mpsc::channel::<Job>();
+            let rx = Arc::new(Mutex::new(rx));
+            for _ in 0..self.threads {
+                let rx = rx.clone();
+                thread::spawn(move || loop {
+                    let job = match rx.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    job();
+                });
+            }
+            tx
+        });
+        let _ = queue.send(Box::new(job));
+    }
+}
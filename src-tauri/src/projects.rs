@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const REGISTRY_FILE: &str = "projects.json";
+
+/// A working folder the app has opened or been told about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub path: String,
+    pub name: String,
+    /// Unix seconds; 0 if added but never opened.
+    pub last_opened: i64,
+    pub pinned: bool,
+    /// Clip totals as of the last time the folder was open.
+    pub clip_count: usize,
+    pub status_counts: BTreeMap<String, usize>,
+    /// Whether the folder is currently reachable. Not persisted.
+    #[serde(skip_deserializing, default)]
+    pub exists: bool,
+}
+
+impl Project {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string()),
+            last_opened: 0,
+            pinned: false,
+            clip_count: 0,
+            status_counts: BTreeMap::new(),
+            exists: true,
+        }
+    }
+}
+
+/// Known working folders, persisted as `projects.json` in the app data dir.
+pub struct ProjectRegistry {
+    path: PathBuf,
+    projects: Mutex<Vec<Project>>,
+}
+
+impl ProjectRegistry {
+    /// Loads the registry; a missing or unreadable file starts it empty.
+    pub fn load(app_data_dir: &Path) -> Self {
+        let path = app_data_dir.join(REGISTRY_FILE);
+        let projects = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| {
+                serde_json::from_str(&contents)
+                    .map_err(|e| eprintln!("[projects] ignoring unreadable {:?}: {}", path, e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            projects: Mutex::new(projects),
+        }
+    }
+
+    fn save(&self, projects: &[Project]) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(projects).map_err(std::io::Error::other)?;
+        fs::write(&self.path, contents)
+    }
+
+    /// Projects with pinned ones first, then most recently opened.
+    pub fn list(&self) -> Vec<Project> {
+        let mut projects = self.projects.lock().unwrap().clone();
+        for project in &mut projects {
+            project.exists = Path::new(&project.path).is_dir();
+        }
+        projects.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+        projects
+    }
+
+    /// Adds a folder without opening it. Adding a known folder is a no-op.
+    pub fn add(&self, path: &Path) -> std::io::Result<Project> {
+        let mut projects = self.projects.lock().unwrap();
+        let key = normalize(path);
+        if let Some(existing) = projects.iter().find(|p| p.path == key.to_string_lossy()) {
+            return Ok(existing.clone());
+        }
+        let project = Project::new(&key);
+        projects.push(project.clone());
+        self.save(&projects)?;
+        Ok(project)
+    }
+
+    /// Drops a folder from the list. Nothing on disk is touched.
+    pub fn remove(&self, path: &str) -> std::io::Result<()> {
+        let mut projects = self.projects.lock().unwrap();
+        let key = normalize(Path::new(path));
+        projects.retain(|p| p.path != key.to_string_lossy() && p.path != path);
+        self.save(&projects)
+    }
+
+    pub fn set_pinned(&self, path: &str, pinned: bool) -> std::io::Result<()> {
+        self.update(Path::new(path), |project| project.pinned = pinned)
+    }
+
+    /// Records that a folder was opened now, adding it if unknown.
+    pub fn touch(&self, path: &Path, status_counts: BTreeMap<String, usize>) -> std::io::Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.update(path, |project| {
+            project.last_opened = now;
+            project.clip_count = status_counts.values().sum();
+            project.status_counts = status_counts;
+        })
+    }
+
+    /// Refreshes the clip totals of a folder, e.g. the one currently open.
+    pub fn update_counts(&self, path: &Path, status_counts: BTreeMap<String, usize>) -> std::io::Result<()> {
+        self.update(path, |project| {
+            project.clip_count = status_counts.values().sum();
+            project.status_counts = status_counts;
+        })
+    }
+
+    fn update(&self, path: &Path, apply: impl FnOnce(&mut Project)) -> std::io::Result<()> {
+        let mut projects = self.projects.lock().unwrap();
+        let key = normalize(path);
+        let index = match projects.iter().position(|p| p.path == key.to_string_lossy()) {
+            Some(index) => index,
+            None => {
+                projects.push(Project::new(&key));
+                projects.len() - 1
+            }
+        };
+        apply(&mut projects[index]);
+        self.save(&projects)
+    }
+}
+
+/// Drops trailing separators and `.` parts so the same folder is not listed
+/// twice. Not canonicalized: folders on unplugged drives must still match.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
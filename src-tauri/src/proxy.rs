@@ -0,0 +1,116 @@
+use crate::commands::AppState;
+use crate::db::{self, Clip, ClipStatus, ProxyStatus};
+use crate::ffmpeg;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::{channel, Receiver, Sender};
+use tauri::{AppHandle, Emitter, Manager};
+
+const QUEUE_CAPACITY: usize = 1024;
+
+#[derive(Clone, serde::Serialize)]
+struct ProxyStatusPayload {
+    clip_id: String,
+    status: ProxyStatus,
+}
+
+/// Background queue that decides which clips need a lightweight preview and
+/// transcodes them one at a time into `.cliptrim/proxies/`.
+pub struct ProxyQueue {
+    sender: Sender<String>,
+    queued: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ProxyQueue {
+    pub fn start(app_handle: AppHandle) -> Self {
+        let (sender, receiver) = channel(QUEUE_CAPACITY);
+        let queued = Arc::new(Mutex::new(HashSet::new()));
+        tauri::async_runtime::spawn(run_worker(app_handle, receiver, queued.clone()));
+        Self { sender, queued }
+    }
+
+    /// Queues a clip for checking. Clips already waiting are not queued twice.
+    pub fn enqueue(&self, clip_id: &str) {
+        if !self.queued.lock().unwrap().insert(clip_id.to_string()) {
+            return;
+        }
+        if self.sender.try_send(clip_id.to_string()).is_err() {
+            eprintln!("[proxy] queue full, dropping clip_id={}", clip_id);
+            self.queued.lock().unwrap().remove(clip_id);
+        }
+    }
+}
+
+/// Whether a scan should queue this clip: it has never been checked, or a
+/// previous session was interrupted while generating its proxy.
+pub fn needs_check(clip: &Clip) -> bool {
+    matches!(clip.proxy_status, ProxyStatus::Unchecked | ProxyStatus::Generating)
+}
+
+/// Whether opening this clip should (re)start proxy work.
+pub fn needs_work_on_open(clip: &Clip) -> bool {
+    needs_check(clip) || matches!(clip.proxy_status, ProxyStatus::Pending | ProxyStatus::Failed)
+}
+
+async fn run_worker(app_handle: AppHandle, mut receiver: Receiver<String>, queued: Arc<Mutex<HashSet<String>>>) {
+    while let Some(clip_id) = receiver.recv().await {
+        if let Err(e) = process_clip(&app_handle, &clip_id).await {
+            eprintln!("[proxy] clip_id={} failed: {}", clip_id, e);
+            set_status(&app_handle, &clip_id, ProxyStatus::Failed, None);
+        }
+        queued.lock().unwrap().remove(&clip_id);
+    }
+}
+
+async fn process_clip(app_handle: &AppHandle, clip_id: &str) -> Result<(), String> {
+    let (clip, proxies_dir) = {
+        let state = app_handle.state::<AppState>();
+        let wd_guard = state.work_dir.lock().unwrap();
+        let Some(wd) = wd_guard.as_ref() else {
+            return Ok(());
+        };
+        let db_guard = state.db.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("DB not initialized")?;
+        match db::get_clip_by_id(conn, clip_id).map_err(|e| e.to_string())? {
+            Some(clip) => (clip, wd.proxies_dir.clone()),
+            // The working folder changed since this clip was queued.
+            None => return Ok(()),
+        }
+    };
+
+    let source = PathBuf::from(&clip.backup_path);
+    let probe = ffmpeg::probe_media(app_handle, &source).await.map_err(|e| e.to_string())?;
+    if !probe.needs_preview_proxy() {
+        set_status(app_handle, clip_id, ProxyStatus::NotNeeded, None);
+        return Ok(());
+    }
+
+    // Transcoding reads the source for minutes; for clips still sitting in IN
+    // that would block the move to BACKUP on Windows, so wait until opened.
+    if clip.status == ClipStatus::New {
+        set_status(app_handle, clip_id, ProxyStatus::Pending, None);
+        return Ok(());
+    }
+
+    let proxy_path = proxies_dir.join(format!("{}.mp4", clip.id));
+    set_status(app_handle, clip_id, ProxyStatus::Generating, None);
+    ffmpeg::generate_preview_proxy(app_handle, &source, &proxy_path, &probe)
+        .await
+        .map_err(|e| e.to_string())?;
+    set_status(app_handle, clip_id, ProxyStatus::Ready, Some(&proxy_path.to_string_lossy()));
+    eprintln!("[proxy] clip_id={} ready at {:?}", clip_id, proxy_path);
+    Ok(())
+}
+
+fn set_status(app_handle: &AppHandle, clip_id: &str, status: ProxyStatus, path: Option<&str>) {
+    let state = app_handle.state::<AppState>();
+    let db_guard = state.db.lock().unwrap();
+    if let Some(conn) = db_guard.as_ref() {
+        let _ = db::update_clip_proxy(conn, clip_id, status, path);
+    }
+    let _ = app_handle.emit("proxy-status-changed", ProxyStatusPayload {
+        clip_id: clip_id.to_string(),
+        status,
+    });
+}
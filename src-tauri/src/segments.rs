@@ -0,0 +1,470 @@
+use crate::db::{Clip, Segment};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// What to do when a new or moved segment overlaps others of the clip.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    #[default]
+    Allow,
+    Reject,
+    /// Grow the segment to cover the ones it overlaps and drop those.
+    Merge,
+    /// Shorten the overlapped segments so they end where this one starts
+    /// or start where it ends.
+    TrimNeighbour,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentError {
+    NegativeStart { start_ms: i64 },
+    EndBeforeStart { start_ms: i64, end_ms: i64 },
+    /// Zero-length, or shorter than one frame.
+    TooShort { length_ms: i64, min_ms: i64 },
+    PastEnd { end_ms: i64, duration_ms: i64 },
+    /// `number` is the overlapped segment's 1-based position in the list.
+    Overlap { number: usize, start_ms: i64, end_ms: i64 },
+    /// Trimming would leave nothing of the overlapped segment.
+    CannotTrim { number: usize },
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativeStart { start_ms } => write!(f, "Segment starts before the clip ({})", format_ms(*start_ms)),
+            Self::EndBeforeStart { start_ms, end_ms } => write!(
+                f,
+                "Segment ends ({}) before it starts ({})",
+                format_ms(*end_ms),
+                format_ms(*start_ms)
+            ),
+            Self::TooShort { length_ms, min_ms } => write!(
+                f,
+                "Segment is {} ms long; it must be at least one frame ({} ms)",
+                length_ms, min_ms
+            ),
+            Self::PastEnd { end_ms, duration_ms } => write!(
+                f,
+                "Segment ends at {}, after the end of the clip ({})",
+                format_ms(*end_ms),
+                format_ms(*duration_ms)
+            ),
+            Self::Overlap { number, start_ms, end_ms } => write!(
+                f,
+                "Segment overlaps segment #{} ({} - {})",
+                number,
+                format_ms(*start_ms),
+                format_ms(*end_ms)
+            ),
+            Self::CannotTrim { number } => write!(
+                f,
+                "Segment #{} would be trimmed away entirely; delete or merge it instead",
+                number
+            ),
+        }
+    }
+}
+
+fn format_ms(ms: i64) -> String {
+    let sign = if ms < 0 { "-" } else { "" };
+    let ms = ms.abs();
+    format!("{}{}:{:02}.{:03}", sign, ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
+
+fn overlaps(a: &Segment, b: &Segment) -> bool {
+    a.start_ms < b.end_ms && b.start_ms < a.end_ms
+}
+
+/// Checks bounds against the clip's cached duration and frame length. Clips
+/// whose duration was never probed are only checked for sane numbers.
+pub fn validate(clip: &Clip, start_ms: i64, end_ms: i64) -> Result<(), SegmentError> {
+    if start_ms < 0 {
+        return Err(SegmentError::NegativeStart { start_ms });
+    }
+    if end_ms < start_ms {
+        return Err(SegmentError::EndBeforeStart { start_ms, end_ms });
+    }
+    let min_ms = clip.frame_ms();
+    if end_ms - start_ms < min_ms {
+        return Err(SegmentError::TooShort { length_ms: end_ms - start_ms, min_ms });
+    }
+    if let Some(duration_ms) = clip.duration_ms {
+        if end_ms > duration_ms {
+            return Err(SegmentError::PastEnd { end_ms, duration_ms });
+        }
+    }
+    Ok(())
+}
+
+/// Validates `candidate` and fits it into the clip's `segments`, replacing
+/// the segment with the same ID or appending it. Returns the new list with
+/// indices renumbered; a merged candidate keeps its ID.
+pub fn place(
+    clip: &Clip,
+    segments: &[Segment],
+    mut candidate: Segment,
+    policy: OverlapPolicy,
+) -> Result<Vec<Segment>, SegmentError> {
+    validate(clip, candidate.start_ms, candidate.end_ms)?;
+
+    let mut list: Vec<Segment> = segments.to_vec();
+    let position = match list.iter().position(|s| s.id == candidate.id) {
+        Some(position) => position,
+        None => {
+            list.push(candidate.clone());
+            list.len() - 1
+        }
+    };
+    let overlapped: Vec<usize> = (0..list.len())
+        .filter(|&i| i != position && overlaps(&list[i], &candidate))
+        .collect();
+
+    match policy {
+        OverlapPolicy::Allow => {}
+        OverlapPolicy::Reject => {
+            if let Some(&i) = overlapped.first() {
+                return Err(SegmentError::Overlap {
+                    number: i + 1,
+                    start_ms: list[i].start_ms,
+                    end_ms: list[i].end_ms,
+                });
+            }
+        }
+        OverlapPolicy::Merge => {
+            // Growing may reach further segments, so repeat until it stops
+            let mut absorbed: Vec<String> = Vec::new();
+            loop {
+                let next: Vec<&Segment> = list
+                    .iter()
+                    .filter(|s| s.id != candidate.id && !absorbed.contains(&s.id) && overlaps(s, &candidate))
+                    .collect();
+                if next.is_empty() {
+                    break;
+                }
+                for other in next {
+                    candidate.start_ms = candidate.start_ms.min(other.start_ms);
+                    candidate.end_ms = candidate.end_ms.max(other.end_ms);
+                    if candidate.label.as_deref().is_none_or(str::is_empty) {
+                        candidate.label = other.label.clone();
+                    }
+                    absorbed.push(other.id.clone());
+                }
+            }
+            list.retain(|s| !absorbed.contains(&s.id));
+        }
+        OverlapPolicy::TrimNeighbour => {
+            let min_ms = clip.frame_ms();
+            for &i in &overlapped {
+                let other = &mut list[i];
+                if other.start_ms < candidate.start_ms && other.end_ms <= candidate.end_ms {
+                    other.end_ms = candidate.start_ms;
+                } else if other.start_ms >= candidate.start_ms && other.end_ms > candidate.end_ms {
+                    other.start_ms = candidate.end_ms;
+                } else {
+                    // One contains the other; trimming would split or erase it
+                    return Err(SegmentError::CannotTrim { number: i + 1 });
+                }
+                if other.end_ms - other.start_ms < min_ms {
+                    return Err(SegmentError::CannotTrim { number: i + 1 });
+                }
+            }
+        }
+    }
+
+    if let Some(slot) = list.iter_mut().find(|s| s.id == candidate.id) {
+        *slot = candidate;
+    }
+    renumber(&mut list);
+    Ok(list)
+}
+
+/// Repairs an existing list: swaps reversed bounds, clamps segments to the
+/// clip, drops ones shorter than a frame and merges overlapping ones into the
+/// earliest listed of them. Order is otherwise kept.
+pub fn normalize(clip: &Clip, segments: Vec<Segment>) -> Vec<Segment> {
+    let min_ms = clip.frame_ms();
+    let mut list: Vec<Segment> = Vec::with_capacity(segments.len());
+    for mut segment in segments {
+        if segment.end_ms < segment.start_ms {
+            std::mem::swap(&mut segment.start_ms, &mut segment.end_ms);
+        }
+        segment.start_ms = segment.start_ms.max(0);
+        if let Some(duration_ms) = clip.duration_ms {
+            segment.end_ms = segment.end_ms.min(duration_ms);
+        }
+        if segment.end_ms - segment.start_ms < min_ms {
+            continue;
+        }
+        list.push(segment);
+    }
+    let mut list = merge_into_earliest(list);
+    renumber(&mut list);
+    list
+}
+
+/// Merges any overlapping pair of `list` into the earlier one until none
+/// overlap.
+fn merge_into_earliest(mut list: Vec<Segment>) -> Vec<Segment> {
+    'outer: loop {
+        for i in 0..list.len() {
+            for j in i + 1..list.len() {
+                if overlaps(&list[i], &list[j]) {
+                    let later = list.remove(j);
+                    let kept = &mut list[i];
+                    kept.start_ms = kept.start_ms.min(later.start_ms);
+                    kept.end_ms = kept.end_ms.max(later.end_ms);
+                    if kept.label.as_deref().is_none_or(str::is_empty) {
+                        kept.label = later.label;
+                    }
+                    continue 'outer;
+                }
+            }
+        }
+        return list;
+    }
+}
+
+/// Whether `ordered` lists each of `current`'s IDs exactly once.
+pub fn is_reordering(current: &[String], ordered: &[String]) -> bool {
+    let known: HashSet<&String> = current.iter().collect();
+    let requested: HashSet<&String> = ordered.iter().collect();
+    requested.len() == ordered.len() && requested == known
+}
+
+/// Segment IDs ordered by where they start, then end, in the clip.
+pub fn order_by_start(segments: &[Segment]) -> Vec<String> {
+    let mut sorted: Vec<&Segment> = segments.iter().collect();
+    // Stable, so segments starting together keep their relative order
+    sorted.sort_by_key(|s| (s.start_ms, s.end_ms));
+    sorted.into_iter().map(|s| s.id.clone()).collect()
+}
+
+fn renumber(list: &mut [Segment]) {
+    for (idx, segment) in list.iter_mut().enumerate() {
+        segment.idx = idx as i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ClipStatus, ProxyStatus};
+
+    /// A 10 s clip at 25 fps, so one frame is 40 ms.
+    fn clip() -> Clip {
+        Clip {
+            id: "clip".to_string(),
+            original_name: "clip.mp4".to_string(),
+            relative_path: "clip.mp4".to_string(),
+            backup_path: "BACKUP/clip.mp4".to_string(),
+            status: ClipStatus::New,
+            created_at: 0,
+            proxy_status: ProxyStatus::Unchecked,
+            proxy_path: None,
+            container: None,
+            video_codec: None,
+            audio_codec: None,
+            size_bytes: None,
+            fingerprint: None,
+            content_hash: None,
+            duration_ms: Some(10_000),
+            fps: Some(25.0),
+        }
+    }
+
+    fn seg(id: &str, start_ms: i64, end_ms: i64) -> Segment {
+        Segment {
+            id: id.to_string(),
+            clip_id: "clip".to_string(),
+            idx: 0,
+            start_ms,
+            end_ms,
+            label: None,
+            export_number: None,
+        }
+    }
+
+    fn bounds(list: &[Segment]) -> Vec<(&str, i64, i64)> {
+        list.iter().map(|s| (s.id.as_str(), s.start_ms, s.end_ms)).collect()
+    }
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn validate_checks_bounds_against_the_clip() {
+        let clip = clip();
+        assert_eq!(validate(&clip, -1, 100), Err(SegmentError::NegativeStart { start_ms: -1 }));
+        assert_eq!(
+            validate(&clip, 500, 400),
+            Err(SegmentError::EndBeforeStart { start_ms: 500, end_ms: 400 })
+        );
+        assert_eq!(
+            validate(&clip, 500, 500),
+            Err(SegmentError::TooShort { length_ms: 0, min_ms: 40 })
+        );
+        assert_eq!(
+            validate(&clip, 500, 539),
+            Err(SegmentError::TooShort { length_ms: 39, min_ms: 40 })
+        );
+        assert_eq!(
+            validate(&clip, 9_000, 10_001),
+            Err(SegmentError::PastEnd { end_ms: 10_001, duration_ms: 10_000 })
+        );
+        assert_eq!(validate(&clip, 500, 540), Ok(()));
+        assert_eq!(validate(&clip, 0, 10_000), Ok(()));
+    }
+
+    #[test]
+    fn validate_skips_the_end_check_for_unprobed_clips() {
+        let clip = Clip { duration_ms: None, fps: None, ..clip() };
+        assert_eq!(validate(&clip, 0, 1), Ok(()));
+        assert_eq!(validate(&clip, 0, 1_000_000), Ok(()));
+    }
+
+    #[test]
+    fn allow_keeps_overlapping_segments() {
+        let list = place(&clip(), &[seg("a", 0, 1000)], seg("b", 500, 1500), OverlapPolicy::Allow).unwrap();
+        assert_eq!(bounds(&list), vec![("a", 0, 1000), ("b", 500, 1500)]);
+        assert_eq!(list.iter().map(|s| s.idx).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn place_replaces_a_segment_with_the_same_id() {
+        let existing = [seg("a", 0, 1000), seg("b", 2000, 3000)];
+        let list = place(&clip(), &existing, seg("a", 100, 1900), OverlapPolicy::Reject).unwrap();
+        assert_eq!(bounds(&list), vec![("a", 100, 1900), ("b", 2000, 3000)]);
+    }
+
+    #[test]
+    fn place_rejects_an_invalid_candidate_under_any_policy() {
+        let result = place(&clip(), &[], seg("a", 0, 20_000), OverlapPolicy::Allow);
+        assert_eq!(result, Err(SegmentError::PastEnd { end_ms: 20_000, duration_ms: 10_000 }));
+    }
+
+    #[test]
+    fn reject_names_the_first_overlapped_segment() {
+        let existing = [seg("a", 0, 1000), seg("b", 2000, 3000)];
+        let result = place(&clip(), &existing, seg("c", 2500, 3500), OverlapPolicy::Reject);
+        assert_eq!(result, Err(SegmentError::Overlap { number: 2, start_ms: 2000, end_ms: 3000 }));
+    }
+
+    #[test]
+    fn touching_segments_do_not_overlap() {
+        let existing = [seg("a", 0, 1000), seg("b", 2000, 3000)];
+        for policy in [OverlapPolicy::Reject, OverlapPolicy::Merge, OverlapPolicy::TrimNeighbour] {
+            let list = place(&clip(), &existing, seg("c", 1000, 2000), policy).unwrap();
+            assert_eq!(bounds(&list), vec![("a", 0, 1000), ("b", 2000, 3000), ("c", 1000, 2000)]);
+        }
+    }
+
+    #[test]
+    fn merge_absorbs_overlapped_segments_into_the_candidate() {
+        let mut a = seg("a", 0, 1000);
+        a.label = Some("intro".to_string());
+        let existing = [a, seg("b", 1200, 2000), seg("c", 5000, 6000)];
+        let list = place(&clip(), &existing, seg("d", 500, 1500), OverlapPolicy::Merge).unwrap();
+        assert_eq!(bounds(&list), vec![("c", 5000, 6000), ("d", 0, 2000)]);
+        assert_eq!(list[1].label.as_deref(), Some("intro"));
+        assert_eq!(list.iter().map(|s| s.idx).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn merge_keeps_growing_until_nothing_overlaps() {
+        // The candidate only reaches `a`, but `a` grown reaches `b`
+        let existing = [seg("a", 0, 1000), seg("b", 900, 2000)];
+        let list = place(&clip(), &existing, seg("c", 100, 200), OverlapPolicy::Merge).unwrap();
+        assert_eq!(bounds(&list), vec![("c", 0, 2000)]);
+    }
+
+    #[test]
+    fn merge_absorbs_a_fully_contained_segment() {
+        let list = place(&clip(), &[seg("a", 400, 600)], seg("b", 0, 1000), OverlapPolicy::Merge).unwrap();
+        assert_eq!(bounds(&list), vec![("b", 0, 1000)]);
+    }
+
+    #[test]
+    fn trim_shortens_neighbours_on_either_side() {
+        let existing = [seg("a", 0, 1000), seg("b", 1200, 2000)];
+        let list = place(&clip(), &existing, seg("c", 800, 1500), OverlapPolicy::TrimNeighbour).unwrap();
+        assert_eq!(bounds(&list), vec![("a", 0, 800), ("b", 1500, 2000), ("c", 800, 1500)]);
+    }
+
+    #[test]
+    fn trim_refuses_to_split_a_containing_segment() {
+        let existing = [seg("a", 0, 1000)];
+        let result = place(&clip(), &existing, seg("b", 200, 300), OverlapPolicy::TrimNeighbour);
+        assert_eq!(result, Err(SegmentError::CannotTrim { number: 1 }));
+    }
+
+    #[test]
+    fn trim_refuses_to_erase_a_contained_segment() {
+        let existing = [seg("a", 1000, 2000), seg("b", 400, 600)];
+        let result = place(&clip(), &existing, seg("c", 0, 1000), OverlapPolicy::TrimNeighbour);
+        assert_eq!(result, Err(SegmentError::CannotTrim { number: 2 }));
+    }
+
+    #[test]
+    fn trim_refuses_to_leave_less_than_a_frame() {
+        let existing = [seg("a", 0, 100)];
+        let result = place(&clip(), &existing, seg("b", 39, 500), OverlapPolicy::TrimNeighbour);
+        assert_eq!(result, Err(SegmentError::CannotTrim { number: 1 }));
+
+        let list = place(&clip(), &existing, seg("b", 40, 500), OverlapPolicy::TrimNeighbour).unwrap();
+        assert_eq!(bounds(&list), vec![("a", 0, 40), ("b", 40, 500)]);
+    }
+
+    #[test]
+    fn normalize_repairs_bounds_and_drops_slivers() {
+        let list = normalize(
+            &clip(),
+            vec![seg("a", 2000, 1000), seg("b", -500, 500), seg("c", 9_000, 12_000), seg("d", 5000, 5020)],
+        );
+        assert_eq!(bounds(&list), vec![("a", 1000, 2000), ("b", 0, 500), ("c", 9_000, 10_000)]);
+        assert_eq!(list.iter().map(|s| s.idx).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn normalize_drops_segments_clamped_to_nothing() {
+        let list = normalize(&clip(), vec![seg("a", 10_000, 11_000), seg("b", 0, 1000)]);
+        assert_eq!(bounds(&list), vec![("b", 0, 1000)]);
+    }
+
+    #[test]
+    fn normalize_merges_overlaps_into_the_earliest_listed() {
+        let mut c = seg("c", 1500, 2500);
+        c.label = Some("middle".to_string());
+        let list = normalize(
+            &clip(),
+            vec![seg("a", 5000, 6000), seg("b", 1000, 2000), c, seg("d", 1200, 1300), seg("e", 2500, 3000)],
+        );
+        assert_eq!(bounds(&list), vec![("a", 5000, 6000), ("b", 1000, 2500), ("e", 2500, 3000)]);
+        assert_eq!(list[1].label.as_deref(), Some("middle"));
+    }
+
+    #[test]
+    fn reordering_must_list_each_segment_once() {
+        let current = ids(&["a", "b", "c"]);
+        assert!(is_reordering(&current, &ids(&["c", "a", "b"])));
+        assert!(is_reordering(&current, &current));
+        assert!(!is_reordering(&current, &ids(&["a", "b"])));
+        assert!(!is_reordering(&current, &ids(&["a", "b", "b"])));
+        assert!(!is_reordering(&current, &ids(&["a", "b", "c", "c"])));
+        assert!(!is_reordering(&current, &ids(&["a", "b", "d"])));
+    }
+
+    #[test]
+    fn order_by_start_breaks_ties_by_end_then_list_order() {
+        let list = [
+            seg("a", 3000, 4000),
+            seg("b", 1000, 3000),
+            seg("c", 1000, 2000),
+            seg("d", 0, 500),
+            seg("e", 1000, 2000),
+        ];
+        assert_eq!(order_by_start(&list), ids(&["d", "c", "e", "b", "a"]));
+    }
+}
@@ -0,0 +1,625 @@
+use crate::commands::AppState;
+use crate::db::{self, ProxyStatus};
+use crate::ffmpeg::FrameFormat;
+use crate::folder_lock::FolderLock;
+use crate::frames::FrameCache;
+use crate::hls;
+use crate::pool::WorkerPool;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use chrono::{DateTime, Utc};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+/// Set this to prefer a fixed port (e.g. for debugging with curl). If it is
+/// taken the server falls back to an ephemeral port.
+const PORT_ENV_VAR: &str = "CLIPTRIM_MEDIA_PORT";
+
+/// Requests are handled concurrently so a slow thumbnail or large range read
+/// does not stall seeking in the player.
+const WORKER_THREADS: usize = 4;
+
+/// Frame renders that may run at once. They have their own threads so cache
+/// misses never hold up a worker serving the player.
+const FRAME_RENDER_THREADS: usize = 2;
+
+static FRAME_POOL: WorkerPool = WorkerPool::new(FRAME_RENDER_THREADS);
+
+/// Origins the webview loads the frontend from. Anything else gets no CORS
+/// access, and requests that announce a foreign origin are refused outright.
+const ALLOWED_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+const DEV_ORIGIN: &str = "http://localhost:1420";
+
+/// Connection details the frontend needs to build media URLs. The token is
+/// generated once per app session and must accompany every request.
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaServerInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+/// Managed state describing whether the media server came up.
+pub enum MediaServerState {
+    Running(MediaServerInfo),
+    Failed(String),
+}
+
+#[derive(Clone, Serialize)]
+struct MediaServerErrorPayload {
+    error: String,
+}
+
+/// Binds the media server, starts its worker threads and registers the
+/// outcome as managed state. A bind failure is reported through
+/// `get_media_server_info` and the `media-server-error` event rather than a
+/// panic. The window is not listening yet when this runs, so the event is
+/// sent again whenever the frontend announces itself with `frontend-ready`.
+pub fn start(app_handle: &AppHandle) {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    let state = match bind() {
+        Ok((server, port)) => {
+            println!("Localhost proxy server listening on port {}", port);
+            let info = MediaServerInfo { port, token };
+            spawn_workers(app_handle, server, &info);
+            MediaServerState::Running(info)
+        }
+        Err(e) => {
+            let error = format!("Media server could not start: {}", e);
+            eprintln!("[media-server] {}", error);
+            let _ = app_handle.emit("media-server-error", MediaServerErrorPayload { error: error.clone() });
+            let replay_handle = app_handle.clone();
+            let replay_error = error.clone();
+            app_handle.listen("frontend-ready", move |_| {
+                let _ = replay_handle.emit("media-server-error", MediaServerErrorPayload { error: replay_error.clone() });
+            });
+            MediaServerState::Failed(error)
+        }
+    };
+    app_handle.manage(state);
+}
+
+fn bind() -> Result<(Arc<Server>, u16), String> {
+    let preferred = std::env::var(PORT_ENV_VAR).ok().and_then(|p| p.parse::<u16>().ok());
+    let server = match preferred.map(|port| Server::http(("127.0.0.1", port))) {
+        Some(Ok(server)) => server,
+        Some(Err(e)) => {
+            eprintln!("[media-server] preferred port unavailable ({}), using an ephemeral port", e);
+            Server::http(("127.0.0.1", 0)).map_err(|e| e.to_string())?
+        }
+        None => Server::http(("127.0.0.1", 0)).map_err(|e| e.to_string())?,
+    };
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .ok_or("server is not listening on an IP socket")?;
+    Ok((Arc::new(server), port))
+}
+
+fn spawn_workers(app_handle: &AppHandle, server: Arc<Server>, info: &MediaServerInfo) {
+    for _ in 0..WORKER_THREADS {
+        let server = server.clone();
+        let app_handle = app_handle.clone();
+        let info = info.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(&app_handle, &info, request);
+            }
+        });
+    }
+}
+
+fn handle_request(app_handle: &AppHandle, info: &MediaServerInfo, request: Request) {
+    let origin = header_value(&request, "Origin");
+    let cors_origin = match origin.as_deref() {
+        Some(o) if is_allowed_origin(o) => Some(o.to_string()),
+        Some(_) => {
+            let _ = request.respond(Response::empty(403));
+            return;
+        }
+        None => None,
+    };
+
+    let (path, query) = parse_url(request.url());
+    if query.get("token") != Some(&info.token) {
+        let _ = request.respond(Response::empty(403));
+        return;
+    }
+
+    match path.as_str() {
+        "/video" => {
+            let Some(clip_id) = query.get("clip") else {
+                let _ = request.respond(Response::empty(400));
+                return;
+            };
+            let prefer_original = query.get("source").map(String::as_str) == Some("original");
+            match resolve_clip_path(app_handle, clip_id, prefer_original) {
+                Some(file_path) => serve_file(request, &file_path, cors_origin),
+                None => {
+                    let _ = request.respond(Response::empty(404));
+                }
+            }
+        }
+        "/frame" => serve_frame(app_handle, request, &query, cors_origin),
+        _ if path.starts_with("/hls/") => serve_hls(app_handle, request, &path, &info.token, cors_origin),
+        _ => {
+            let _ = request.respond(Response::empty(404));
+        }
+    }
+}
+
+/// Serves `/hls/<clip_id>/index.m3u8` and `/hls/<clip_id>/seg_NNNNN.ts`,
+/// generating the playlist and chunks on demand. Refused with 409 while the
+/// folder is open read-only. Anything not yet cached is
+/// handed to the HLS generation pool along with the request, so this worker
+/// is free again at once.
+fn serve_hls(app_handle: &AppHandle, request: Request, path: &str, token: &str, cors_origin: Option<String>) {
+    let mut parts = path.trim_start_matches("/hls/").splitn(2, '/');
+    let (Some(clip_id), Some(file_name)) = (parts.next(), parts.next()) else {
+        let _ = request.respond(Response::empty(404));
+        return;
+    };
+    // The cache lives in `.cliptrim/`, which a read-only instance must not
+    // write; the player falls back to `/video` there
+    let state = app_handle.state::<AppState>();
+    if state.folder_lock.lock().unwrap().as_ref().is_none_or(FolderLock::is_lost) {
+        let _ = request.respond(Response::empty(409));
+        return;
+    }
+    let Some(source) = resolve_clip_path(app_handle, clip_id, true) else {
+        let _ = request.respond(Response::empty(404));
+        return;
+    };
+    let Some(hls_dir) = state
+        .work_dir
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|wd| wd.hls_dir.clone())
+    else {
+        let _ = request.respond(Response::empty(404));
+        return;
+    };
+
+    let app_handle = app_handle.clone();
+    let clip_id = clip_id.to_string();
+    if file_name == "index.m3u8" {
+        let query = format!("token={}", token);
+        hls::spawn_generation(move || match hls::playlist(&app_handle, &hls_dir, &clip_id, &source, &query) {
+            Ok(playlist) => {
+                let mut response = Response::from_string(playlist)
+                    .with_header(header("Content-Type", "application/vnd.apple.mpegurl"))
+                    .with_header(header("Cache-Control", "no-cache"));
+                for h in cors_headers(cors_origin) {
+                    response.add_header(h);
+                }
+                let _ = request.respond(response);
+            }
+            Err(e) => {
+                eprintln!("[hls] playlist for clip_id={} failed: {}", clip_id, e);
+                let _ = request.respond(Response::empty(500));
+            }
+        });
+        return;
+    }
+
+    let Some(index) = hls::parse_segment_name(file_name) else {
+        let _ = request.respond(Response::empty(404));
+        return;
+    };
+    if let Some(segment_path) = hls::cached_segment(&hls_dir, &clip_id, index) {
+        serve_file(request, &segment_path, cors_origin);
+        return;
+    }
+    hls::spawn_generation(move || match hls::segment(&app_handle, &hls_dir, &clip_id, &source, index) {
+        Ok(segment_path) => serve_file(request, &segment_path, cors_origin),
+        Err(e) => {
+            eprintln!("[hls] segment {} for clip_id={} failed: {}", index, clip_id, e);
+            let _ = request.respond(Response::empty(500));
+        }
+    });
+}
+
+/// Serves `/frame?clip=<id>&t=<ms>&w=<width>&fmt=jpg|png` from the shared
+/// frame cache. Frames never change for a given URL, so the browser may cache them.
+fn serve_frame(app_handle: &AppHandle, request: Request, query: &HashMap<String, String>, cors_origin: Option<String>) {
+    let (Some(clip_id), Some(time_ms)) = (
+        query.get("clip"),
+        query.get("t").and_then(|t| t.parse::<i64>().ok()),
+    ) else {
+        let _ = request.respond(Response::empty(400));
+        return;
+    };
+    let width = query.get("w").and_then(|w| w.parse::<u32>().ok());
+    let Some(format) = FrameFormat::parse(query.get("fmt").map(String::as_str).unwrap_or("jpg")) else {
+        let _ = request.respond(Response::empty(400));
+        return;
+    };
+    let Some(source) = resolve_clip_path(app_handle, clip_id, true) else {
+        let _ = request.respond(Response::empty(404));
+        return;
+    };
+
+    if let Some(bytes) = app_handle.state::<FrameCache>().cached(clip_id, time_ms, width, format) {
+        respond_frame(request, &bytes, format, cors_origin);
+        return;
+    }
+
+    // Rendering runs ffmpeg; keep it off the workers so range requests for
+    // the player are still served while scrubbing
+    let app_handle = app_handle.clone();
+    let clip_id = clip_id.clone();
+    FRAME_POOL.spawn(move || {
+        let cache = app_handle.state::<FrameCache>();
+        let rendered = tauri::async_runtime::block_on(cache.render(&app_handle, &clip_id, &source, time_ms, width, format));
+        match rendered {
+            Ok(bytes) => respond_frame(request, &bytes, format, cors_origin),
+            Err(e) => {
+                eprintln!("[media-server] frame for clip_id={} at {}ms failed: {}", clip_id, time_ms, e);
+                let _ = request.respond(Response::empty(500));
+            }
+        }
+    });
+}
+
+fn respond_frame(request: Request, bytes: &[u8], format: FrameFormat, cors_origin: Option<String>) {
+    let mut response = Response::from_data(bytes)
+        .with_header(header("Content-Type", format.mime_type()))
+        .with_header(header("Cache-Control", "private, max-age=3600"));
+    for h in cors_headers(cors_origin) {
+        response.add_header(h);
+    }
+    let _ = request.respond(response);
+}
+
+fn cors_headers(cors_origin: Option<String>) -> Vec<Header> {
+    match cors_origin {
+        Some(origin) => vec![
+            header("Access-Control-Allow-Origin", &origin),
+            header("Vary", "Origin"),
+        ],
+        None => Vec::new(),
+    }
+}
+
+fn is_allowed_origin(origin: &str) -> bool {
+    ALLOWED_ORIGINS.contains(&origin) || (cfg!(debug_assertions) && origin == DEV_ORIGIN)
+}
+
+fn header_value(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn parse_url(url: &str) -> (String, HashMap<String, String>) {
+    let (path, query_str) = url.split_once('?').unwrap_or((url, ""));
+    let query = query_str
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| {
+            let value = urlencoding::decode(v)
+                .map(|d| d.into_owned())
+                .unwrap_or_else(|_| v.to_string());
+            (k.to_string(), value)
+        })
+        .collect();
+    (path.to_string(), query)
+}
+
+/// Looks up the clip's file and returns its canonical path, but only if it
+/// lives inside the active working folder. A ready preview proxy is preferred
+/// over the original unless `prefer_original` is set.
+fn resolve_clip_path(app_handle: &AppHandle, clip_id: &str, prefer_original: bool) -> Option<PathBuf> {
+    let state = app_handle.state::<AppState>();
+    let roots: Vec<PathBuf> = state
+        .work_dir
+        .lock()
+        .unwrap()
+        .as_ref()?
+        .media_roots()
+        .into_iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect();
+    let clip = {
+        let db_guard = state.db.lock().unwrap();
+        db::get_clip_by_id(db_guard.as_ref()?, clip_id).ok()??
+    };
+
+    let source = match (&clip.proxy_path, clip.proxy_status) {
+        (Some(proxy_path), ProxyStatus::Ready) if !prefer_original => proxy_path.clone(),
+        _ => clip.backup_path.clone(),
+    };
+
+    let file_path = PathBuf::from(&source).canonicalize().ok()?;
+    if !roots.iter().any(|root| file_path.starts_with(root)) {
+        eprintln!("[media-server] refusing to serve {:?} outside the working folders", file_path);
+        return None;
+    }
+    Some(file_path)
+}
+
+/// Outcome of interpreting a `Range` header against a file of known size.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Parses a single `bytes=` range (`a-b`, `a-`, or suffix `-n`). Multi-range
+/// and malformed headers fall back to the full body, which RFC 9110 permits.
+fn parse_range(header: &str, file_size: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", "") => return ByteRange::Full,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (file_size.saturating_sub(n), file_size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(s) => (s, file_size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(s), Ok(e)) if s <= e => (s, e.min(file_size.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if file_size == 0 || start >= file_size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end }
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "ts" => "video/mp2t",
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+fn http_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|d| d.timestamp())
+}
+
+/// Conditional requests: If-None-Match takes precedence over If-Modified-Since.
+fn is_not_modified(if_none_match: Option<&str>, if_modified_since: Option<&str>, etag: &str, modified_secs: i64) -> bool {
+    match if_none_match {
+        Some(tags) => tags.split(',').any(|t| t.trim() == etag || t.trim() == "*"),
+        None => if_modified_since
+            .and_then(parse_http_date)
+            .is_some_and(|since| modified_secs <= since),
+    }
+}
+
+/// If-Range: only honour the Range header if the client's copy is current.
+fn range_allowed(if_range: Option<&str>, etag: &str, modified_secs: i64) -> bool {
+    match if_range.map(str::trim) {
+        Some(v) if v.starts_with('"') || v.starts_with("W/") => v == etag,
+        Some(v) => parse_http_date(v).is_some_and(|since| modified_secs <= since),
+        None => true,
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn serve_file(request: Request, path: &Path, cors_origin: Option<String>) {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        let _ = request.respond(Response::empty(405).with_header(header("Allow", "GET, HEAD")));
+        return;
+    }
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => {
+            let _ = request.respond(Response::empty(404));
+            return;
+        }
+    };
+    let metadata = match file.metadata() {
+        Ok(m) => m,
+        Err(_) => {
+            let _ = request.respond(Response::empty(500));
+            return;
+        }
+    };
+
+    let file_size = metadata.len();
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let modified_secs = DateTime::<Utc>::from(modified).timestamp();
+    let etag = format!("\"{:x}-{:x}\"", file_size, modified_secs);
+
+    let mut headers = vec![
+        header("Content-Type", content_type_for(path)),
+        header("Accept-Ranges", "bytes"),
+        header("ETag", &etag),
+        header("Last-Modified", &http_date(modified)),
+        header("Cache-Control", "no-cache"),
+    ];
+    headers.extend(cors_headers(cors_origin));
+
+    let not_modified = is_not_modified(
+        header_value(&request, "If-None-Match").as_deref(),
+        header_value(&request, "If-Modified-Since").as_deref(),
+        &etag,
+        modified_secs,
+    );
+    if not_modified {
+        let mut response = Response::empty(304);
+        for h in headers {
+            response.add_header(h);
+        }
+        let _ = request.respond(response);
+        return;
+    }
+
+    let range = match header_value(&request, "Range") {
+        Some(r) if range_allowed(header_value(&request, "If-Range").as_deref(), &etag, modified_secs) => {
+            parse_range(&r, file_size)
+        }
+        _ => ByteRange::Full,
+    };
+
+    let (status, start, length) = match range {
+        ByteRange::Full => (200, 0, file_size),
+        ByteRange::Partial { start, end } => {
+            headers.push(header(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", start, end, file_size),
+            ));
+            (206, start, end - start + 1)
+        }
+        ByteRange::Unsatisfiable => {
+            headers.push(header("Content-Range", &format!("bytes */{}", file_size)));
+            let mut response = Response::empty(416);
+            for h in headers {
+                response.add_header(h);
+            }
+            let _ = request.respond(response);
+            return;
+        }
+    };
+
+    let mut file = file;
+    if file.seek(SeekFrom::Start(start)).is_err() {
+        let _ = request.respond(Response::empty(500));
+        return;
+    }
+
+    // Stream straight from disk; a fixed Content-Length keeps tiny_http from
+    // switching to chunked encoding for large bodies.
+    let response = Response::new(
+        StatusCode(status),
+        headers,
+        file.take(length),
+        Some(length as usize),
+        None,
+    )
+    .with_chunked_threshold(usize::MAX);
+    let _ = request.respond(response);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const ETAG: &str = "\"3e8-5f5e100\"";
+    const MODIFIED: i64 = 100_000_000;
+
+    fn date(secs: i64) -> String {
+        http_date(SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64))
+    }
+
+    #[test]
+    fn parses_closed_and_open_ranges() {
+        assert_eq!(parse_range("bytes=0-499", 1000), ByteRange::Partial { start: 0, end: 499 });
+        assert_eq!(parse_range(" bytes= 10 - 20 ", 1000), ByteRange::Partial { start: 10, end: 20 });
+        assert_eq!(parse_range("bytes=500-", 1000), ByteRange::Partial { start: 500, end: 999 });
+        assert_eq!(parse_range("bytes=999-999", 1000), ByteRange::Partial { start: 999, end: 999 });
+        // The end is clamped to the file
+        assert_eq!(parse_range("bytes=900-5000", 1000), ByteRange::Partial { start: 900, end: 999 });
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-200", 1000), ByteRange::Partial { start: 800, end: 999 });
+        assert_eq!(parse_range("bytes=-5000", 1000), ByteRange::Partial { start: 0, end: 999 });
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_end() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=1500-2000", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-10", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn falls_back_to_the_full_body() {
+        for header in ["items=0-1", "bytes=0-1,5-6", "bytes=500-400", "bytes=abc-", "bytes=-x", "bytes=-", "bytes=5"] {
+            assert_eq!(parse_range(header, 1000), ByteRange::Full, "{}", header);
+        }
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence() {
+        assert!(is_not_modified(Some(ETAG), None, ETAG, MODIFIED));
+        assert!(is_not_modified(Some(&format!("\"other\", {}", ETAG)), None, ETAG, MODIFIED));
+        assert!(is_not_modified(Some("*"), None, ETAG, MODIFIED));
+        // A stale tag wins over a current date
+        assert!(!is_not_modified(Some("\"other\""), Some(&date(MODIFIED)), ETAG, MODIFIED));
+    }
+
+    #[test]
+    fn if_modified_since_compares_dates() {
+        assert!(is_not_modified(None, Some(&date(MODIFIED)), ETAG, MODIFIED));
+        assert!(is_not_modified(None, Some(&date(MODIFIED + 60)), ETAG, MODIFIED));
+        assert!(!is_not_modified(None, Some(&date(MODIFIED - 1)), ETAG, MODIFIED));
+        assert!(!is_not_modified(None, Some("yesterday"), ETAG, MODIFIED));
+        assert!(!is_not_modified(None, None, ETAG, MODIFIED));
+    }
+
+    #[test]
+    fn if_range_needs_the_current_entity_tag() {
+        assert!(range_allowed(None, ETAG, MODIFIED));
+        assert!(range_allowed(Some(ETAG), ETAG, MODIFIED));
+        assert!(range_allowed(Some(&format!(" {} ", ETAG)), ETAG, MODIFIED));
+        assert!(!range_allowed(Some("\"other\""), ETAG, MODIFIED));
+        // Weak tags never match for ranges
+        assert!(!range_allowed(Some(&format!("W/{}", ETAG)), ETAG, MODIFIED));
+    }
+
+    #[test]
+    fn if_range_accepts_a_current_date() {
+        assert!(range_allowed(Some(&date(MODIFIED)), ETAG, MODIFIED));
+        assert!(!range_allowed(Some(&date(MODIFIED - 1)), ETAG, MODIFIED));
+        assert!(!range_allowed(Some("not a date"), ETAG, MODIFIED));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::db::{self, ClipStatus, Segment};
+use crate::file_manager::{RetentionAction, WorkingDirectory};
+use chrono::Utc;
+use rusqlite::Connection;
+use std::path::Path;
+
+/// Re-encoding at CRF 18 can exceed the source bitrate, so the estimate
+/// from the source is scaled up by this factor.
+const EXPORT_SIZE_FACTOR: f64 = 1.5;
+
+/// Free space always left on the output drive on top of the estimate.
+const EXPORT_HEADROOM_BYTES: u64 = 256 * 1024 * 1024;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Rough size of the files `export_segments` will write, derived from the
+/// source's average bitrate and the total segment length.
+pub fn estimate_export_bytes(source_bytes: u64, source_duration_sec: f64, segments: &[Segment]) -> u64 {
+    if source_duration_sec <= 0.0 {
+        return source_bytes;
+    }
+    let bytes_per_sec = source_bytes as f64 / source_duration_sec;
+    let total_sec: f64 = segments
+        .iter()
+        .map(|s| (s.end_ms - s.start_ms).max(0) as f64 / 1000.0)
+        .sum();
+    (bytes_per_sec * total_sec * EXPORT_SIZE_FACTOR) as u64
+}
+
+/// Fails with a readable message if `dir`'s drive cannot take `needed_bytes`.
+pub fn ensure_free_space(dir: &Path, needed_bytes: u64) -> Result<(), String> {
+    let available = fs2::available_space(dir).map_err(|e| format!("Could not check free space on {:?}: {}", dir, e))?;
+    if available < needed_bytes + EXPORT_HEADROOM_BYTES {
+        return Err(format!(
+            "Not enough free space in {}: the export needs about {} but only {} is available",
+            dir.display(),
+            format_bytes(needed_bytes + EXPORT_HEADROOM_BYTES),
+            format_bytes(available),
+        ));
+    }
+    Ok(())
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= GB {
+        format!("{:.1} GB", bytes as f64 / GB)
+    } else {
+        format!("{:.0} MB", bytes as f64 / MB)
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CleanupItem {
+    pub clip_id: String,
+    pub path: String,
+    pub size_bytes: u64,
+    /// `max_age` or `max_size`.
+    pub reason: &'static str,
+}
+
+/// Backups the retention policy selects, and what happened to them.
+#[derive(Debug, serde::Serialize)]
+pub struct CleanupPlan {
+    pub action: RetentionAction,
+    pub items: Vec<CleanupItem>,
+    pub reclaimable_bytes: u64,
+    /// Paths that could not be removed or archived.
+    pub failed: Vec<String>,
+}
+
+/// Chooses which backups of `done` clips the folder's retention policy
+/// allows removing, oldest finished first. Age counts from when the clip
+/// became `done`, or from ingest for clips finished before status history
+/// was kept. Clips in any other state are never touched.
+pub fn plan_cleanup(wd: &WorkingDirectory, conn: &Connection) -> rusqlite::Result<CleanupPlan> {
+    let policy = &wd.config.retention;
+    let done_times = db::get_done_times(conn)?;
+    let done_at = |clip: &db::Clip| done_times.get(&clip.id).copied().unwrap_or(clip.created_at);
+    let mut clips = db::get_clips(conn)?;
+    clips.sort_by_key(|c| done_at(c));
+
+    let now = Utc::now().timestamp();
+    let backup_total: u64 = clips
+        .iter()
+        .filter(|c| wd.is_in_backup(Path::new(&c.backup_path)))
+        .filter_map(|c| std::fs::metadata(&c.backup_path).ok())
+        .map(|m| m.len())
+        .sum();
+    let size_limit = policy.max_backup_gb.map(|gb| (gb * 1024.0 * 1024.0 * 1024.0) as u64);
+
+    let mut items = Vec::new();
+    let mut remaining = backup_total;
+    for clip in clips.iter().filter(|c| c.status == ClipStatus::Done) {
+        let path = Path::new(&clip.backup_path);
+        if !wd.is_in_backup(path) {
+            continue;
+        }
+        let Ok(meta) = std::fs::metadata(path) else {
+            continue;
+        };
+
+        let too_old = policy
+            .max_age_days
+            .is_some_and(|days| now - done_at(clip) > days as i64 * SECS_PER_DAY);
+        let over_size = size_limit.is_some_and(|limit| remaining > limit);
+        let reason = if too_old {
+            "max_age"
+        } else if over_size {
+            "max_size"
+        } else {
+            continue;
+        };
+
+        remaining -= meta.len();
+        items.push(CleanupItem {
+            clip_id: clip.id.clone(),
+            path: clip.backup_path.clone(),
+            size_bytes: meta.len(),
+            reason,
+        });
+    }
+
+    Ok(CleanupPlan {
+        action: policy.action,
+        reclaimable_bytes: items.iter().map(|i| i.size_bytes).sum(),
+        items,
+        failed: Vec::new(),
+    })
+}
+
+/// Deletes or archives the backups in `plan` and updates their clips.
+pub fn apply_cleanup(wd: &WorkingDirectory, conn: &Connection, plan: &mut CleanupPlan) -> rusqlite::Result<()> {
+    for item in &plan.items {
+        let path = Path::new(&item.path);
+        let result = match plan.action {
+            RetentionAction::Delete => std::fs::remove_file(path).map(|_| None),
+            RetentionAction::Archive => wd.archive_backup(path).map(Some),
+        };
+        match result {
+            Ok(Some(archived)) => db::update_clip_backup_path(conn, &item.clip_id, &archived.to_string_lossy())?,
+            Ok(None) => {
+                if let Ok(Some(clip)) = db::get_clip_by_id(conn, &item.clip_id) {
+                    if let Some(proxy_path) = clip.proxy_path {
+                        let _ = std::fs::remove_file(proxy_path);
+                    }
+                }
+                let _ = std::fs::remove_dir_all(wd.hls_dir.join(&item.clip_id));
+                db::clear_clip_backup(conn, &item.clip_id)?;
+            }
+            Err(e) => {
+                eprintln!("[retention] {:?}: {}", path, e);
+                plan.failed.push(item.path.clone());
+            }
+        }
+    }
+    Ok(())
+}
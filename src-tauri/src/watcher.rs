@@ -0,0 +1,203 @@
+use crate::file_manager::WorkingDirectory;
+use crate::ingest;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// How often pending files are re-checked for a stable size.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Removals wait this long before the clip is dropped, so that a rename
+/// (remove + create) is ingested first and recognised by its fingerprint.
+const REMOVAL_DELAY: Duration = Duration::from_secs(6);
+
+#[derive(Clone, serde::Serialize)]
+struct ClipsChangedPayload {
+    added: usize,
+    renamed: usize,
+    removed: usize,
+}
+
+struct PendingFile {
+    size: u64,
+    changed_at: Instant,
+}
+
+/// Watches IN for new and removed media. New files are ingested only once
+/// their size has stopped changing for `ingest::SETTLE_TIME`, so recordings
+/// and copies in progress are not picked up half-written.
+///
+/// Dropping the watcher stops it; `select_working_folder` replaces it when
+/// the working folder changes.
+pub struct InWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl InWatcher {
+    pub fn start(app_handle: AppHandle, wd: &WorkingDirectory) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel::<Event>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        })?;
+        watcher.watch(&wd.in_dir, RecursiveMode::Recursive)?;
+
+        let wd = wd.clone();
+        thread::spawn(move || {
+            let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+            let mut removed: HashMap<PathBuf, Instant> = HashMap::new();
+            loop {
+                match rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(event) => handle_event(&wd, event, &mut pending, &mut removed),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // The watcher was dropped: the working folder changed.
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                ingest_settled(&app_handle, &mut pending);
+                report_removed(&app_handle, &mut removed);
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+fn is_hidden(wd: &WorkingDirectory, path: &Path) -> bool {
+    path.strip_prefix(&wd.in_dir)
+        .map(|rel| rel.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')))
+        .unwrap_or(true)
+}
+
+fn is_media(wd: &WorkingDirectory, path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| wd.config.accepts_extension(&ext.to_string_lossy()))
+}
+
+fn handle_event(
+    wd: &WorkingDirectory,
+    event: Event,
+    pending: &mut HashMap<PathBuf, PendingFile>,
+    removed: &mut HashMap<PathBuf, Instant>,
+) {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => {
+            for path in event.paths.into_iter().filter(|p| !is_hidden(wd, p)) {
+                if path.is_dir() {
+                    // A folder moved into IN only reports itself, not its contents.
+                    let files = wd.scan_in_folder().unwrap_or_default();
+                    for file in files.into_iter().filter(|f| f.starts_with(&path)) {
+                        removed.remove(&file);
+                        track(pending, file);
+                    }
+                } else if path.exists() && is_media(wd, &path) {
+                    removed.remove(&path);
+                    track(pending, path);
+                } else if !path.exists() && is_media(wd, &path) {
+                    // Renames surface as modify events on the old name.
+                    pending.remove(&path);
+                    removed.insert(path, Instant::now());
+                }
+            }
+        }
+        EventKind::Remove(_) => {
+            for path in event.paths.into_iter().filter(|p| is_media(wd, p)) {
+                pending.remove(&path);
+                removed.insert(path, Instant::now());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn track(pending: &mut HashMap<PathBuf, PendingFile>, path: PathBuf) {
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let entry = pending.entry(path).or_insert(PendingFile {
+        size,
+        changed_at: Instant::now(),
+    });
+    if entry.size != size {
+        entry.size = size;
+        entry.changed_at = Instant::now();
+    }
+}
+
+/// Ingests pending files whose size has not changed for the settle time.
+fn ingest_settled(app_handle: &AppHandle, pending: &mut HashMap<PathBuf, PendingFile>) {
+    for (path, file) in pending.iter_mut() {
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size != file.size {
+            file.size = size;
+            file.changed_at = Instant::now();
+        }
+    }
+
+    let settled: Vec<PathBuf> = pending
+        .iter()
+        .filter(|(_, f)| f.size > 0 && f.changed_at.elapsed() >= ingest::SETTLE_TIME)
+        .map(|(p, _)| p.clone())
+        .collect();
+    if settled.is_empty() {
+        return;
+    }
+
+    match tauri::async_runtime::block_on(ingest::ingest_files(app_handle, settled.clone())) {
+        Ok(summary) => {
+            // Files ingest still considers in use stay pending for another try
+            for path in settled.iter().filter(|p| !summary.unsettled.contains(p)) {
+                pending.remove(path);
+            }
+            for path in &summary.unsettled {
+                if let Some(file) = pending.get_mut(path) {
+                    file.changed_at = Instant::now();
+                }
+            }
+            if summary.added + summary.renamed > 0 {
+                let _ = app_handle.emit("clips-changed", ClipsChangedPayload {
+                    added: summary.added,
+                    renamed: summary.renamed,
+                    removed: 0,
+                });
+            }
+        }
+        // Keep everything pending and retry after another settle time
+        Err(_) => {
+            for path in &settled {
+                if let Some(file) = pending.get_mut(path) {
+                    file.changed_at = Instant::now();
+                }
+            }
+        }
+    }
+}
+
+/// Drops clips for removals older than `REMOVAL_DELAY`. If that fails they
+/// are tried again after another delay.
+fn report_removed(app_handle: &AppHandle, removed: &mut HashMap<PathBuf, Instant>) {
+    let due: Vec<PathBuf> = removed
+        .iter()
+        .filter(|(_, at)| at.elapsed() >= REMOVAL_DELAY)
+        .map(|(p, _)| p.clone())
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+    for path in &due {
+        removed.remove(path);
+    }
+    match ingest::remove_vanished(app_handle, &due) {
+        Ok(0) => {}
+        Ok(removed) => {
+            let _ = app_handle.emit("clips-changed", ClipsChangedPayload { added: 0, renamed: 0, removed });
+        }
+        Err(_) => {
+            for path in due {
+                removed.insert(path, Instant::now());
+            }
+        }
+    }
+}
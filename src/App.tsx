@@ -1,5 +1,6 @@
 import { useState, useEffect, useRef, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { emit, listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import { Group as PanelGroup, Panel, Separator as PanelResizeHandle } from "react-resizable-panels";
 import { Sidebar } from "./components/Sidebar";
@@ -13,10 +14,15 @@ import { AboutModal } from "./components/AboutModal";
 import { ToastContainer, ToastMessage } from "./components/Toast";
 import { LoadingOverlay } from "./components/LoadingOverlay";
 import { JobsPanel } from "./components/JobsPanel";
-import { Clip, Segment, VideoInfo } from "./types";
+import { Clip, MediaServerInfo, Project, ProxyStatus, Segment, SelectFolderError, VideoInfo } from "./types";
 
 export type EditMarkerTarget = 'in' | 'out' | { type: 'segmentIn' | 'segmentOut', segmentId: string };
 
+// Recordings at least this long play through the server's HLS mode, which
+// seeks without range-reading the whole file, where the webview plays HLS natively
+const HLS_MIN_DURATION_MS = 60 * 60 * 1000;
+const canPlayHls = document.createElement('video').canPlayType('application/vnd.apple.mpegurl') !== '';
+
 export function App() {
   const [playbackRate, setPlaybackRate] = useState(1.0);
   const [clips, setClips] = useState<Clip[]>([]);
@@ -24,6 +30,8 @@ export function App() {
   const [activeClip, setActiveClip] = useState<Clip | null>(null);
   const [segments, setSegments] = useState<Segment[]>([]);
   const [workingFolder, setWorkingFolder] = useState<string | null>(null);
+  const [mediaServer, setMediaServer] = useState<MediaServerInfo | null>(null);
+  const [isReadOnly, setIsReadOnly] = useState(false);
 
   // Loading State
   const [isLoading, setIsLoading] = useState(false);
@@ -74,18 +82,74 @@ export function App() {
 
   // Initialize
   useEffect(() => {
+    invoke<MediaServerInfo>("get_media_server_info")
+      .then(setMediaServer)
+      // The failure itself is shown from the media-server-error event
+      .catch((e) => console.error("Failed to get media server info:", e));
+    const unlistenServerError = listen<{ error: string }>("media-server-error", (event) => {
+      addToast("Video preview unavailable: " + event.payload.error, 'error');
+    });
+    // Errors raised before this listener existed are sent again on request
+    unlistenServerError.then(() => emit("frontend-ready"));
+    const unlistenProxyStatus = listen<{ clip_id: string; status: ProxyStatus }>("proxy-status-changed", (event) => {
+      const { clip_id, status } = event.payload;
+      setClips(prev => prev.map(c => c.id === clip_id ? { ...c, proxy_status: status } : c));
+      setActiveClip(prev => {
+        if (!prev || prev.id !== clip_id) return prev;
+        if (status === 'generating') addToast("Preparing preview for smoother playback...", 'info');
+        return { ...prev, proxy_status: status };
+      });
+    });
+    const unlistenBackupProgress = listen<{ clip_id: string; percent: number }>("backup-progress", (event) => {
+      setLoadingMessage(`Backing up original... ${event.payload.percent}%`);
+    });
+    const unlistenClipsChanged = listen<{ added: number; renamed: number; removed: number }>("clips-changed", (event) => {
+      const { added, removed } = event.payload;
+      invoke<Clip[]>("get_clips")
+        .then(setClips)
+        .catch((e) => console.error("Failed to refresh clips", e));
+      if (added > 0) addToast(`${added} new clip${added === 1 ? '' : 's'} in IN`, 'info');
+      if (removed > 0) addToast(`${removed} clip${removed === 1 ? '' : 's'} removed from IN`, 'info');
+    });
+
+    const unlistenFolderLockLost = listen<{ host: string | null; pid: number | null }>("folder-lock-lost", (event) => {
+      const { host, pid } = event.payload;
+      const holder = host ? ` on ${host} (process ${pid})` : '';
+      setIsReadOnly(true);
+      addToast(`Another ClipTrim instance${holder} took over this folder; it is now read-only`, 'error');
+    });
+
     // Attempt to auto-load the last used folder
     const initFolder = async () => {
-      if (settings.lastUsedFolder) {
+      let lastFolder = settings.lastUsedFolder;
+      if (!lastFolder) {
         try {
-          await invoke("select_working_folder", { path: settings.lastUsedFolder });
-          setWorkingFolder(settings.lastUsedFolder);
+          const projects = await invoke<Project[]>("list_projects");
+          lastFolder = projects
+            .filter(p => p.exists && p.last_opened > 0)
+            .sort((a, b) => b.last_opened - a.last_opened)[0]?.path;
+        } catch (e) {
+          console.warn("Failed to list projects:", e);
+        }
+      }
+      if (lastFolder) {
+        try {
+          await selectFolder(lastFolder);
+          setWorkingFolder(lastFolder);
         } catch (e) {
           console.warn("Failed to load last used folder:", e);
         }
       }
     };
     initFolder();
+
+    return () => {
+      unlistenServerError.then((unlisten) => unlisten());
+      unlistenProxyStatus.then((unlisten) => unlisten());
+      unlistenClipsChanged.then((unlisten) => unlisten());
+      unlistenBackupProgress.then((unlisten) => unlisten());
+      unlistenFolderLockLost.then((unlisten) => unlisten());
+    };
   }, []);
 
   useEffect(() => {
@@ -149,6 +213,23 @@ export function App() {
     }
   };
 
+  // Opens a folder, offering read-only access if another instance holds it
+  const selectFolder = async (path: string) => {
+    try {
+      await invoke("select_working_folder", { path });
+      setIsReadOnly(false);
+    } catch (e) {
+      const error = e as SelectFolderError;
+      if (error.kind !== 'locked') throw error.message;
+      if (!window.confirm(`${error.message}.\n\nOpen it read-only instead?`)) throw error.message;
+      await invoke("select_working_folder", { path, readOnly: true }).catch((e: SelectFolderError) => {
+        throw e.message;
+      });
+      setIsReadOnly(true);
+      addToast("Opened read-only: clips can be viewed but not moved, edited or exported", 'info');
+    }
+  };
+
   const handleSelectFolder = async () => {
     try {
       const selected = await open({
@@ -159,7 +240,7 @@ export function App() {
 
       if (selected && typeof selected === 'string') {
         try {
-          await invoke("select_working_folder", { path: selected });
+          await selectFolder(selected);
           setWorkingFolder(selected);
           handleSettingsChange({ ...settings, lastUsedFolder: selected });
           addToast(`Working folder set to ${selected}`, 'success');
@@ -172,8 +253,16 @@ export function App() {
     }
   };
 
-  const activeVideoUrl = activeClip
-    ? `http://localhost:50123/video?path=${encodeURIComponent(activeClip.backup_path)}`
+  // The HLS cache is written into the folder, so read-only instances stream the file directly
+  const useHls = canPlayHls
+    && !isReadOnly
+    && activeClip?.proxy_status !== 'ready'
+    && (activeClip?.duration_ms ?? 0) >= HLS_MIN_DURATION_MS;
+  const activeVideoUrl = activeClip && mediaServer
+    ? useHls
+      ? `http://127.0.0.1:${mediaServer.port}/hls/${encodeURIComponent(activeClip.id)}/index.m3u8?token=${mediaServer.token}`
+      // The proxy flag changes the URL so the player reloads once a preview proxy is ready
+      : `http://127.0.0.1:${mediaServer.port}/video?clip=${encodeURIComponent(activeClip.id)}&token=${mediaServer.token}&proxy=${activeClip.proxy_status === 'ready' ? 1 : 0}`
     : null;
 
   const handleVolumeChange = (volume: number) => {
@@ -207,10 +296,16 @@ export function App() {
       const newSeg = await invoke<Segment>("add_segment", {
         clipId: activeClipId,
         startMs: inMarker,
-        endMs: outMarker
+        endMs: outMarker,
+        overlapPolicy: settings.overlapPolicy
       });
       console.log("Segment added successfully:", newSeg);
-      setSegments([...segments, newSeg]);
+      if (settings.overlapPolicy === 'merge' || settings.overlapPolicy === 'trim_neighbour') {
+        // Other segments may have been merged or trimmed
+        setSegments(await invoke<Segment[]>("get_segments", { clipId: activeClipId }));
+      } else {
+        setSegments([...segments, newSeg]);
+      }
       setInMarker(null);
       setOutMarker(null);
       addLog('success', `Added segment from ${inMarker}ms to ${outMarker}ms`);
@@ -231,13 +326,56 @@ export function App() {
 
   const handleDeleteSegment = async (id: string) => {
     try {
-      await invoke("delete_segment", { segmentId: id });
-      setSegments(segments.filter(s => s.id !== id));
+      const remaining = await invoke<Segment[]>("delete_segment", { segmentId: id });
+      setSegments(remaining);
     } catch (e) {
       console.error(e);
     }
   };
 
+  const handleUndoRedo = async (command: 'undo' | 'redo') => {
+    if (!activeClipId) return;
+    try {
+      setSegments(await invoke<Segment[]>(command, { clipId: activeClipId }));
+      addLog('info', command === 'undo' ? 'Undid last segment edit' : 'Redid segment edit');
+    } catch (e: any) {
+      addToast(e.toString(), 'info');
+    }
+  };
+
+  const handleMoveSegment = async (id: string, offset: -1 | 1) => {
+    if (!activeClipId) return;
+    const from = segments.findIndex(s => s.id === id);
+    const to = from + offset;
+    if (from < 0 || to < 0 || to >= segments.length) return;
+    const orderedIds = segments.map(s => s.id);
+    [orderedIds[from], orderedIds[to]] = [orderedIds[to], orderedIds[from]];
+    try {
+      setSegments(await invoke<Segment[]>("reorder_segments", { clipId: activeClipId, orderedIds }));
+    } catch (e: any) {
+      addToast("Failed to reorder segments: " + e.toString(), 'error');
+    }
+  };
+
+  const handleNormalizeSegments = async () => {
+    if (!activeClipId) return;
+    try {
+      setSegments(await invoke<Segment[]>("normalize_segments", { clipId: activeClipId }));
+      addLog('info', 'Normalized segments');
+    } catch (e: any) {
+      addToast("Failed to normalize segments: " + e.toString(), 'error');
+    }
+  };
+
+  const handleSortSegments = async () => {
+    if (!activeClipId) return;
+    try {
+      setSegments(await invoke<Segment[]>("sort_segments_by_start", { clipId: activeClipId }));
+    } catch (e: any) {
+      addToast("Failed to sort segments: " + e.toString(), 'error');
+    }
+  };
+
   const handleUpdateLabel = async (id: string, label: string) => {
     try {
       await invoke("update_segment_label", { segmentId: id, label });
@@ -268,9 +406,14 @@ export function App() {
         const updatedSeg = await invoke<Segment>("update_segment_bounds", {
           segmentId: target.segmentId,
           startMs: Math.floor(start_ms),
-          endMs: Math.floor(end_ms)
+          endMs: Math.floor(end_ms),
+          overlapPolicy: settings.overlapPolicy
         });
-        setSegments(prev => prev.map(s => s.id === updatedSeg.id ? updatedSeg : s));
+        if (settings.overlapPolicy === 'merge' || settings.overlapPolicy === 'trim_neighbour') {
+          setSegments(await invoke<Segment[]>("get_segments", { clipId: segToUpdate.clip_id }));
+        } else {
+          setSegments(prev => prev.map(s => s.id === updatedSeg.id ? updatedSeg : s));
+        }
       } catch (e) {
         console.error("Failed to update segment bounds via nudge", e);
         addToast("Failed to update segment bounds: " + e, "error");
@@ -295,7 +438,7 @@ export function App() {
     addToast(`Export job started for ${activeClip?.original_name}`, 'success');
 
     try {
-      await invoke("export_segments", { clipId: activeClipId });
+      await invoke("export_segments", { clipId: activeClipId, streamCopy: settings.fastExport });
       // The background rust task is running and emitting events now.
 
       // We can auto-advance smoothly without waiting for it.
@@ -365,6 +508,8 @@ export function App() {
                   onSetOut={handleSetOut}
                   onAddSegment={handleAddSegment}
                   onDeleteSelected={handleDeleteSelected}
+                  onUndo={() => handleUndoRedo('undo')}
+                  onRedo={() => handleUndoRedo('redo')}
                   onExport={handleExport}
                   onNextClip={handleNextClip}
                   onPrevClip={handlePrevClip}
@@ -407,6 +552,9 @@ export function App() {
                       onVolumeChange={handleVolumeChange}
                       onMuteChange={handleMuteChange}
                       isEditMode={isEditMode}
+                      frameUrl={activeClip && mediaServer
+                        ? (timeMs) => `http://127.0.0.1:${mediaServer.port}/frame?clip=${encodeURIComponent(activeClip.id)}&t=${timeMs}&w=320&token=${mediaServer.token}`
+                        : undefined}
                     />
                   </Panel>
                 </>
@@ -427,6 +575,9 @@ export function App() {
                 <SegmentList
                   segments={segments}
                   onDelete={handleDeleteSegment}
+                  onMove={handleMoveSegment}
+                  onSortByStart={handleSortSegments}
+                  onNormalize={handleNormalizeSegments}
                   onUpdateLabel={handleUpdateLabel}
                   onUpdateBounds={() => { }}
                   onSeek={handleSeek}
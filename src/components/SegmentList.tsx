@@ -1,9 +1,12 @@
 import { Segment } from '../types';
-import { Trash2, Edit2, Play } from 'lucide-react';
+import { Trash2, Edit2, Play, ChevronUp, ChevronDown, ArrowDownWideNarrow, Wand2 } from 'lucide-react';
 
 interface SegmentListProps {
     segments: Segment[];
     onDelete: (id: string) => void;
+    onMove: (id: string, offset: -1 | 1) => void;
+    onSortByStart: () => void;
+    onNormalize: () => void;
     onUpdateLabel: (id: string, label: string) => void;
     onUpdateBounds: (id: string, startMs: number, endMs: number) => void;
     onSeek: (timeMs: number) => void;
@@ -17,13 +20,21 @@ const formatTime = (ms: number) => {
     return `${minutes.toString().padStart(2, '0')}:${seconds.toString().padStart(2, '0')}.${frames.toString().padStart(2, '0')}`;
 };
 
-export function SegmentList({ segments, onDelete, onUpdateLabel, onSeek }: SegmentListProps) {
+export function SegmentList({ segments, onDelete, onMove, onSortByStart, onNormalize, onUpdateLabel, onSeek }: SegmentListProps) {
     return (
         <div className="h-full flex flex-col bg-[#1e1e1e] border-l border-[#2d2d2d] w-full text-zinc-300">
             <div className="px-3 py-2 border-b border-[#2d2d2d]">
                 <h2 className="text-sm font-semibold text-zinc-300 uppercase tracking-widest flex justify-between items-center">
                     SEGMENTS
-                    <span className="bg-[#2d2d2d] text-zinc-400 text-[10px] py-0.5 px-2 rounded normal-case tracking-normal">{segments.length}</span>
+                    <span className="flex items-center gap-1">
+                        <button onClick={onSortByStart} disabled={segments.length < 2} title="Sort by Start Time" className="p-1 text-zinc-400 hover:text-zinc-100 hover:bg-[#3d3d3d] rounded transition-colors disabled:opacity-30 disabled:hover:bg-transparent">
+                            <ArrowDownWideNarrow size={14} />
+                        </button>
+                        <button onClick={onNormalize} disabled={segments.length === 0} title="Fix Segments (clamp to clip, drop empty, merge overlaps)" className="p-1 text-zinc-400 hover:text-zinc-100 hover:bg-[#3d3d3d] rounded transition-colors disabled:opacity-30 disabled:hover:bg-transparent">
+                            <Wand2 size={14} />
+                        </button>
+                        <span className="bg-[#2d2d2d] text-zinc-400 text-[10px] py-0.5 px-2 rounded normal-case tracking-normal">{segments.length}</span>
+                    </span>
                 </h2>
             </div>
 
@@ -35,8 +46,19 @@ export function SegmentList({ segments, onDelete, onUpdateLabel, onSeek }: Segme
                         {segments.map((seg, i) => (
                             <div key={seg.id} className="group relative border-b border-[#2d2d2d] hover:bg-[#252526] transition-colors p-3 flex flex-col gap-2">
                                 <div className="flex justify-between items-center">
-                                    <span className="text-xs font-mono font-semibold text-zinc-300">Segment #{i + 1}</span>
+                                    <span className="text-xs font-mono font-semibold text-zinc-300">
+                                        Segment #{i + 1}
+                                        {seg.export_number != null && (
+                                            <span className="ml-2 text-[10px] font-normal text-zinc-500" title="Exported files keep this number">trim{seg.export_number.toString().padStart(2, '0')}</span>
+                                        )}
+                                    </span>
                                     <div className="flex gap-1 opacity-100 md:opacity-0 md:group-hover:opacity-100 transition-opacity">
+                                        <button onClick={() => onMove(seg.id, -1)} disabled={i === 0} title="Move Up" className="p-1 text-zinc-400 hover:text-zinc-100 hover:bg-[#3d3d3d] rounded transition-colors disabled:opacity-30 disabled:hover:bg-transparent">
+                                            <ChevronUp size={14} />
+                                        </button>
+                                        <button onClick={() => onMove(seg.id, 1)} disabled={i === segments.length - 1} title="Move Down" className="p-1 text-zinc-400 hover:text-zinc-100 hover:bg-[#3d3d3d] rounded transition-colors disabled:opacity-30 disabled:hover:bg-transparent">
+                                            <ChevronDown size={14} />
+                                        </button>
                                         <button onClick={() => onSeek(seg.start_ms)} title="Go to Start" className="p-1 text-zinc-400 hover:text-zinc-100 hover:bg-[#3d3d3d] rounded transition-colors">
                                             <Play size={14} />
                                         </button>
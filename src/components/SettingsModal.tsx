@@ -19,8 +19,13 @@ export interface Keybinds {
     speedDown: string;
 }
 
+export type OverlapPolicy = 'allow' | 'reject' | 'merge' | 'trim_neighbour';
+
 export interface AppSettings {
     scrubDuration: number;
+    overlapPolicy: OverlapPolicy;
+    /** Stream-copy exports instead of re-encoding them. */
+    fastExport: boolean;
     volume: number;
     isMuted: boolean;
     keybinds: Keybinds;
@@ -29,6 +34,8 @@ export interface AppSettings {
 
 export const defaultSettings: AppSettings = {
     scrubDuration: 1.0,
+    overlapPolicy: 'allow',
+    fastExport: false,
     volume: 1.0,
     isMuted: false,
     keybinds: {
@@ -190,6 +197,40 @@ export function SettingsModal({ isOpen, onClose, settings, onSettingsChange }: S
                                             className="w-32 bg-[#3c3c3c] border border-transparent hover:bg-[#464646] rounded-none px-3 py-1.5 text-zinc-200 text-sm focus:outline-none focus:border-[#007fd4] transition-colors"
                                         />
                                     </div>
+
+                                    <div className="flex flex-col gap-2">
+                                        <h4 className="text-zinc-200 text-sm font-medium">Overlapping Segments</h4>
+                                        <span className="text-xs text-zinc-500 mb-2">
+                                            What happens when a new or moved segment overlaps an existing one.
+                                        </span>
+                                        <select
+                                            name="overlapPolicy"
+                                            value={settings.overlapPolicy}
+                                            onChange={(e) => onSettingsChange({ ...settings, overlapPolicy: e.target.value as OverlapPolicy })}
+                                            className="w-64 bg-[#3c3c3c] border border-transparent hover:bg-[#464646] rounded-none px-3 py-1.5 text-zinc-200 text-sm focus:outline-none focus:border-[#007fd4] transition-colors"
+                                        >
+                                            <option value="allow">Allow overlaps</option>
+                                            <option value="reject">Refuse the edit</option>
+                                            <option value="merge">Merge into one segment</option>
+                                            <option value="trim_neighbour">Trim the other segment</option>
+                                        </select>
+                                    </div>
+
+                                    <div className="flex flex-col gap-2">
+                                        <h4 className="text-zinc-200 text-sm font-medium">Export Mode</h4>
+                                        <span className="text-xs text-zinc-500 mb-2">
+                                            Fast exports copy the video without re-encoding, but each segment starts on the keyframe at or before its IN point. Clips whose codecs the output format cannot hold are always re-encoded.
+                                        </span>
+                                        <select
+                                            name="fastExport"
+                                            value={settings.fastExport ? 'fast' : 'exact'}
+                                            onChange={(e) => onSettingsChange({ ...settings, fastExport: e.target.value === 'fast' })}
+                                            className="w-64 bg-[#3c3c3c] border border-transparent hover:bg-[#464646] rounded-none px-3 py-1.5 text-zinc-200 text-sm focus:outline-none focus:border-[#007fd4] transition-colors"
+                                        >
+                                            <option value="exact">Frame-exact (re-encode)</option>
+                                            <option value="fast">Fast (stream copy)</option>
+                                        </select>
+                                    </div>
                                 </div>
                             )}
 
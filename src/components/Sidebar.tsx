@@ -1,5 +1,5 @@
 import { useState, useMemo, useEffect } from 'react';
-import { Clip } from '../types';
+import { Clip, ClipStatus } from '../types';
 import { Folder, Film, CheckCircle, Clock, ExternalLink, Search, ArrowUpDown } from 'lucide-react';
 import { openPath } from '@tauri-apps/plugin-opener';
 
@@ -41,7 +41,7 @@ export function Sidebar({ clips, activeClipId, onSelectClip, onSelectFolder, wor
         // Filter
         if (searchQuery.trim()) {
             const query = searchQuery.toLowerCase();
-            result = result.filter(clip => clip.original_name.toLowerCase().includes(query));
+            result = result.filter(clip => clip.relative_path.toLowerCase().includes(query));
         }
 
         // Sort
@@ -52,12 +52,12 @@ export function Sidebar({ clips, activeClipId, onSelectClip, onSelectFolder, wor
                 case 'date_desc':
                     return b.created_at - a.created_at;
                 case 'name_asc':
-                    return a.original_name.localeCompare(b.original_name);
+                    return a.relative_path.localeCompare(b.relative_path);
                 case 'name_desc':
-                    return b.original_name.localeCompare(a.original_name);
+                    return b.relative_path.localeCompare(a.relative_path);
                 case 'status':
                     // Custom order: in_progress First -> pending -> done
-                    const statusWeight = (status: string) => {
+                    const statusWeight = (status: ClipStatus) => {
                         if (status === 'in_progress') return 0;
                         if (status === 'done') return 2;
                         return 1; // pending
@@ -182,7 +182,7 @@ export function Sidebar({ clips, activeClipId, onSelectClip, onSelectFolder, wor
                             ) : (
                                 <Film size={14} className="text-zinc-500 shrink-0" />
                             )}
-                            <span className="truncate text-[13px] font-mono">{clip.original_name}</span>
+                            <span className="truncate text-[13px] font-mono" title={clip.relative_path}>{clip.relative_path}</span>
                         </button>
                     ))
                 )}
@@ -15,13 +15,20 @@ interface TimelineProps {
     onVolumeChange?: (volume: number) => void;
     onMuteChange?: (isMuted: boolean) => void;
     isEditMode: boolean;
+    /** URL of a still frame at the given time, shown while hovering the track. */
+    frameUrl?: (timeMs: number) => string | null;
 }
 
+// Hover previews snap to this grid so nearby positions share a cached frame
+const PREVIEW_STEP_MS = 500;
+const PREVIEW_WIDTH = 160;
+
 export function Timeline({
     durationMs, currentTimeMs, inMarker, outMarker, segments, onSeek,
-    volume = 1, isMuted = false, playbackRate = 1, onVolumeChange, onMuteChange, isEditMode
+    volume = 1, isMuted = false, playbackRate = 1, onVolumeChange, onMuteChange, isEditMode, frameUrl
 }: TimelineProps) {
     const [zoomLevel, setZoomLevel] = useState(1);
+    const [hoverPreview, setHoverPreview] = useState<{ timeMs: number; x: number; top: number } | null>(null);
     const containerRef = useRef<HTMLDivElement>(null);
     const prevZoomRef = useRef(1);
     const isPanning = useRef(false);
@@ -211,6 +218,14 @@ export function Timeline({
                         const percent = (e.clientX - rect.left) / rect.width;
                         onSeek(percent * durationMs);
                     }}
+                    onMouseMove={(e) => {
+                        if (!frameUrl || isEditMode || isPanning.current || durationMs <= 0) return;
+                        const rect = e.currentTarget.getBoundingClientRect();
+                        const percent = Math.min(1, Math.max(0, (e.clientX - rect.left) / rect.width));
+                        const timeMs = Math.min(durationMs, Math.round(percent * durationMs / PREVIEW_STEP_MS) * PREVIEW_STEP_MS);
+                        setHoverPreview({ timeMs, x: e.clientX, top: rect.top });
+                    }}
+                    onMouseLeave={() => setHoverPreview(null)}
                 >
                     {/* Edit Mode Banner overlay */}
                     {isEditMode && (
@@ -306,6 +321,19 @@ export function Timeline({
                     </div>
                 </div>
             </div>
+
+            {/* Hover preview, fixed so the scroll container does not clip it */}
+            {hoverPreview && frameUrl?.(hoverPreview.timeMs) && (
+                <div
+                    className="fixed z-50 pointer-events-none -translate-x-1/2 -translate-y-full border border-[#3d3d3d] bg-black shadow-lg"
+                    style={{ left: hoverPreview.x, top: hoverPreview.top - 8, width: PREVIEW_WIDTH }}
+                >
+                    <img src={frameUrl(hoverPreview.timeMs)!} width={PREVIEW_WIDTH} className="block" alt="" />
+                    <div className="text-[10px] text-zinc-300 font-mono text-center py-0.5">
+                        {(hoverPreview.timeMs / 1000).toFixed(1)}s
+                    </div>
+                </div>
+            )}
         </div>
     );
 }
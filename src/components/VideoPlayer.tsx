@@ -12,6 +12,8 @@ interface VideoPlayerProps {
     onSetOut: () => void;
     onAddSegment: () => void;
     onDeleteSelected: () => void;
+    onUndo: () => void;
+    onRedo: () => void;
     onExport: () => void;
     onNextClip: () => void;
     onPrevClip: () => void;
@@ -57,6 +59,8 @@ export function VideoPlayer({
     onSetOut,
     onAddSegment,
     onDeleteSelected,
+    onUndo,
+    onRedo,
     onExport,
     onNextClip,
     onPrevClip,
@@ -99,6 +103,8 @@ export function VideoPlayer({
         onSetOut,
         onAddSegment,
         onDeleteSelected,
+        onUndo,
+        onRedo,
         onExport,
         onNextClip,
         onPrevClip,
@@ -121,6 +127,8 @@ export function VideoPlayer({
             onSetOut,
             onAddSegment,
             onDeleteSelected,
+            onUndo,
+            onRedo,
             onExport,
             onNextClip,
             onPrevClip,
@@ -145,6 +153,14 @@ export function VideoPlayer({
                 return;
             }
 
+            // Undo/redo keep the usual modifier shortcuts rather than a rebindable key
+            if ((e.ctrlKey || e.metaKey) && (e.key.toLowerCase() === 'z' || e.key.toLowerCase() === 'y')) {
+                e.preventDefault();
+                if (e.key.toLowerCase() === 'y' || e.shiftKey) callbacksRef.current.onRedo();
+                else callbacksRef.current.onUndo();
+                return;
+            }
+
             if (!videoRef.current) return;
             const video = videoRef.current;
             const kb = callbacksRef.current.keybinds;
@@ -333,7 +349,7 @@ export function VideoPlayer({
                     autoPlay={true}
                     preload="auto"
                 >
-                    <source src={videoUrl} type="video/mp4" />
+                    <source src={videoUrl} />
                 </video>
             ) : (
                 <div className="text-zinc-500 flex flex-col items-center">
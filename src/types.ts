@@ -1,9 +1,51 @@
+export type ClipStatus =
+    | 'new'
+    | 'in_progress'
+    | 'queued'
+    | 'exporting'
+    | 'done'
+    | 'failed'
+    | 'skipped'
+    | 'rejected'
+    | 'missing';
+
+export type ProxyStatus =
+    | 'unchecked'
+    | 'not_needed'
+    | 'pending'
+    | 'generating'
+    | 'ready'
+    | 'failed';
+
 export interface Clip {
     id: string;
     original_name: string;
+    relative_path: string;
     backup_path: string;
-    status: string;
+    status: ClipStatus;
     created_at: number;
+    proxy_status: ProxyStatus;
+    proxy_path?: string;
+    container?: string;
+    video_codec?: string;
+    audio_codec?: string;
+    size_bytes?: number;
+    fingerprint?: string;
+    content_hash?: string;
+    duration_ms?: number;
+    fps?: number;
+}
+
+export interface StatusChange {
+    from: ClipStatus | null;
+    to: ClipStatus;
+    changed_at: number;
+}
+
+export interface DuplicateGroup {
+    fingerprint: string;
+    confirmed: boolean;
+    clips: Clip[];
 }
 
 export interface Segment {
@@ -13,9 +55,66 @@ export interface Segment {
     start_ms: number;
     end_ms: number;
     label?: string;
+    export_number?: number;
 }
 
 export interface VideoInfo {
     duration_sec: number;
     fps: number;
 }
+
+export interface MediaServerInfo {
+    port: number;
+    token: string;
+}
+
+export interface SuggestedSegment {
+    start_ms: number;
+    end_ms: number;
+    peak_ms: number;
+    score: number;
+}
+
+export interface RemovalSummary {
+    clip_id: string;
+    original_name: string;
+    segment_count: number;
+    files: string[];
+    failed: string[];
+}
+
+export interface IntegrityReport {
+    missing: { clip_id: string; relative_path: string; path: string; status: ClipStatus }[];
+    size_mismatches: { clip_id: string; path: string; expected_bytes: number; actual_bytes: number }[];
+    orphaned_backups: string[];
+    orphaned_outputs: string[];
+    missing_outputs: string[];
+    orphaned_segments: string[];
+}
+
+export interface RepairReport {
+    relinked: { clip_id: string; path: string }[];
+    marked_missing: string[];
+    removed_segments: number;
+}
+
+export interface CleanupPlan {
+    action: 'delete' | 'archive';
+    items: { clip_id: string; path: string; size_bytes: number; reason: 'max_age' | 'max_size' }[];
+    reclaimable_bytes: number;
+    failed: string[];
+}
+
+export interface Project {
+    path: string;
+    name: string;
+    last_opened: number;
+    pinned: boolean;
+    clip_count: number;
+    status_counts: Record<string, number>;
+    exists: boolean;
+}
+
+export type SelectFolderError =
+    | { kind: 'locked'; message: string; host: string; pid: number; acquired_at: number }
+    | { kind: 'other'; message: string };